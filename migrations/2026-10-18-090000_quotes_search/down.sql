@@ -0,0 +1,3 @@
+DROP INDEX quotes_search_vector_idx;
+
+ALTER TABLE quotes DROP COLUMN search_vector;
@@ -0,0 +1,7 @@
+ALTER TABLE quotes
+  ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', coalesce(quote, '')), 'A') ||
+    setweight(to_tsvector('english', coalesce(author, '')), 'B')
+  ) STORED;
+
+CREATE INDEX quotes_search_vector_idx ON quotes USING GIN (search_vector);
@@ -0,0 +1,5 @@
+CREATE TABLE daily_quotes (
+  day DATE NOT NULL PRIMARY KEY,
+  quote_id VARCHAR NOT NULL REFERENCES quotes (id) ON DELETE CASCADE,
+  pinned BOOLEAN NOT NULL DEFAULT FALSE
+);
@@ -0,0 +1,7 @@
+DROP INDEX quotes_author_id_idx;
+
+ALTER TABLE quotes DROP COLUMN author_id;
+
+DROP TABLE authors;
+
+DROP FUNCTION slugify(TEXT);
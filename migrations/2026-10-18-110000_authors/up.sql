@@ -0,0 +1,32 @@
+-- Must stay in sync with `db::entities::author::slugify`: Unicode letters and digits are kept,
+-- lowercased, everything else separates words. The ICU root collation makes [:alnum:] and lower()
+-- follow Unicode whatever the LC_CTYPE of the database, which must be UTF8.
+CREATE FUNCTION slugify(value TEXT) RETURNS TEXT AS $$
+    SELECT trim(both '-' from regexp_replace(lower(value COLLATE "und-x-icu"), '[^[:alnum:]]+', '-', 'g'));
+$$ LANGUAGE sql IMMUTABLE;
+
+CREATE TABLE authors (
+  id VARCHAR NOT NULL PRIMARY KEY,
+  name VARCHAR NOT NULL,
+  slug VARCHAR NOT NULL UNIQUE,
+  bio TEXT,
+  birth_date DATE,
+  death_date DATE
+);
+
+-- One author per normalized name, "Foo" and "foo " end up being the same person
+INSERT INTO authors (id, name, slug)
+SELECT DISTINCT ON (slugify(author)) gen_random_uuid()::VARCHAR, trim(author), slugify(author)
+FROM quotes
+ORDER BY slugify(author), trim(author);
+
+ALTER TABLE quotes ADD COLUMN author_id VARCHAR REFERENCES authors (id);
+
+UPDATE quotes
+SET author_id = authors.id, author = authors.name
+FROM authors
+WHERE authors.slug = slugify(quotes.author);
+
+ALTER TABLE quotes ALTER COLUMN author_id SET NOT NULL;
+
+CREATE INDEX quotes_author_id_idx ON quotes (author_id);
@@ -0,0 +1,8 @@
+DROP INDEX quotes_updated_at_id_idx;
+DROP INDEX quotes_created_at_id_idx;
+
+DROP TRIGGER set_updated_at ON quotes;
+
+ALTER TABLE quotes
+  DROP COLUMN updated_at,
+  DROP COLUMN created_at;
@@ -0,0 +1,9 @@
+ALTER TABLE quotes
+  ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+SELECT diesel_manage_updated_at('quotes');
+
+-- Keyset pagination sorts on the timestamp then the id
+CREATE INDEX quotes_created_at_id_idx ON quotes (created_at, id);
+CREATE INDEX quotes_updated_at_id_idx ON quotes (updated_at, id);
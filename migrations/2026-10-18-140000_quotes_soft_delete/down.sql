@@ -0,0 +1,3 @@
+DROP INDEX quotes_deleted_at_idx;
+
+ALTER TABLE quotes DROP COLUMN deleted_at;
@@ -0,0 +1,4 @@
+ALTER TABLE quotes ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- Used by the purge job, live quotes are not indexed
+CREATE INDEX quotes_deleted_at_idx ON quotes (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,14 @@
+CREATE TABLE quote_revisions (
+  quote_id VARCHAR NOT NULL REFERENCES quotes (id) ON DELETE CASCADE,
+  revision INTEGER NOT NULL,
+  author VARCHAR NOT NULL,
+  author_id VARCHAR NOT NULL,
+  quote TEXT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (quote_id, revision)
+);
+
+-- The current content of existing quotes is their first revision
+INSERT INTO quote_revisions (quote_id, revision, author, author_id, quote, created_at)
+SELECT id, 1, author, author_id, quote, updated_at
+FROM quotes;
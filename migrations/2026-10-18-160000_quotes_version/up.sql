@@ -0,0 +1,3 @@
+-- Incremented on every change of the quote, exposed as its ETag. Revisions are numbered after it,
+-- existing quotes start at version 1 like their first revision
+ALTER TABLE quotes ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,9 @@
+CREATE TABLE revoked_tokens (
+  jti VARCHAR NOT NULL PRIMARY KEY,
+  subject VARCHAR,
+  expires_at TIMESTAMPTZ NOT NULL,
+  revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Entries are pruned once the token expired
+CREATE INDEX revoked_tokens_expires_at ON revoked_tokens (expires_at);
@@ -0,0 +1,12 @@
+CREATE TABLE api_keys (
+  id VARCHAR NOT NULL PRIMARY KEY,
+  name VARCHAR NOT NULL,
+  owner VARCHAR NOT NULL,
+  -- SHA-256 of the key, the key itself is only shown at creation
+  key_hash VARCHAR NOT NULL UNIQUE,
+  scope VARCHAR NOT NULL DEFAULT '',
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMPTZ,
+  last_used_at TIMESTAMPTZ,
+  revoked_at TIMESTAMPTZ
+);
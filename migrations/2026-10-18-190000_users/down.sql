@@ -0,0 +1,3 @@
+ALTER TABLE quotes DROP COLUMN created_by;
+DROP TABLE refresh_tokens;
+DROP TABLE users;
@@ -0,0 +1,26 @@
+CREATE TABLE users (
+  id VARCHAR NOT NULL PRIMARY KEY,
+  username VARCHAR NOT NULL UNIQUE,
+  -- Argon2id PHC string
+  password_hash VARCHAR NOT NULL,
+  scope VARCHAR NOT NULL DEFAULT '',
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE refresh_tokens (
+  id VARCHAR NOT NULL PRIMARY KEY,
+  -- SHA-256 of the token, the token itself is only sent to the client
+  token_hash VARCHAR NOT NULL UNIQUE,
+  user_id VARCHAR NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  -- Tokens rotated from the same login, revoked together when one is reused
+  family_id VARCHAR NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMPTZ NOT NULL,
+  used_at TIMESTAMPTZ,
+  revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX refresh_tokens_family_id_idx ON refresh_tokens (family_id);
+
+-- Subject of the credentials the quote was created with, unknown for older quotes
+ALTER TABLE quotes ADD COLUMN created_by VARCHAR;
@@ -0,0 +1,6 @@
+DROP TABLE quote_revisions;
+DROP TABLE daily_quotes;
+DROP TABLE quote_tags;
+DROP TABLE tags;
+DROP TABLE quotes;
+DROP TABLE authors;
@@ -0,0 +1,55 @@
+-- Same tables as the Postgres migrations, without the full text search column
+CREATE TABLE authors (
+  id TEXT NOT NULL PRIMARY KEY,
+  name TEXT NOT NULL,
+  slug TEXT NOT NULL UNIQUE,
+  bio TEXT,
+  birth_date DATE,
+  death_date DATE
+);
+
+CREATE TABLE quotes (
+  id TEXT NOT NULL PRIMARY KEY,
+  author TEXT NOT NULL,
+  quote TEXT NOT NULL,
+  author_id TEXT NOT NULL REFERENCES authors (id),
+  -- Stored as text written by Diesel, which sorts chronologically
+  created_at TIMESTAMPTZ NOT NULL,
+  updated_at TIMESTAMPTZ NOT NULL,
+  deleted_at TIMESTAMPTZ,
+  version INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX quotes_author_id_idx ON quotes (author_id);
+CREATE INDEX quotes_created_at_id_idx ON quotes (created_at, id);
+CREATE INDEX quotes_updated_at_id_idx ON quotes (updated_at, id);
+CREATE INDEX quotes_deleted_at_idx ON quotes (deleted_at) WHERE deleted_at IS NOT NULL;
+
+CREATE TABLE tags (
+  id TEXT NOT NULL PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE quote_tags (
+  quote_id TEXT NOT NULL REFERENCES quotes (id) ON DELETE CASCADE,
+  tag_id TEXT NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+  PRIMARY KEY (quote_id, tag_id)
+);
+
+CREATE INDEX quote_tags_tag_id_idx ON quote_tags (tag_id);
+
+CREATE TABLE daily_quotes (
+  day DATE NOT NULL PRIMARY KEY,
+  quote_id TEXT NOT NULL REFERENCES quotes (id) ON DELETE CASCADE,
+  pinned BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE quote_revisions (
+  quote_id TEXT NOT NULL REFERENCES quotes (id) ON DELETE CASCADE,
+  revision INTEGER NOT NULL,
+  author TEXT NOT NULL,
+  author_id TEXT NOT NULL,
+  quote TEXT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL,
+  PRIMARY KEY (quote_id, revision)
+);
@@ -0,0 +1 @@
+ALTER TABLE quotes ADD COLUMN created_by TEXT;
@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use crate::config::env::Config;
+use crate::db::store;
+use crate::http::auth::JwtSettings;
+use crate::http::oidc::OidcProvider;
+
+pub mod quotes;
+pub mod users;
+
+pub type CliError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Quotes API, served when no command is given.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum Command {
+    /// Serves the API
+    Serve,
+    /// Applies the pending migrations then exits
+    Migrate,
+    /// Adds sample quotes when there are none yet
+    Seed,
+    /// Prints a bearer token signed like the issued ones, for JWT_ISSUER and JWT_AUDIENCE
+    IssueToken {
+        /// Who the token is issued to
+        #[arg(long)]
+        sub: String,
+        /// Comma separated scopes, e.g. `admin`
+        #[arg(long, value_delimiter = ',')]
+        scopes: Vec<String>,
+        /// Validity in seconds
+        #[arg(long, default_value_t = 3600, value_parser = clap::value_parser!(i64).range(1..))]
+        ttl: i64,
+    },
+    /// Creates a user logging in at `POST /auth/login`, the password is read from the standard input
+    CreateUser {
+        #[arg(long)]
+        username: String,
+        /// Comma separated scopes, e.g. `quotes:read,quotes:write`
+        #[arg(long, value_delimiter = ',')]
+        scopes: Vec<String>,
+    },
+    /// Creates the quotes of a JSON file holding an array of `{"author", "quote", "tags"}`
+    Import {
+        file: PathBuf,
+    },
+    /// Writes every quote to a JSON file that `import` reads back
+    Export {
+        file: PathBuf,
+    },
+    /// Checks the configuration, the JWT keys, the OIDC issuer, the database connection and the migrations
+    CheckConfig,
+}
+
+/// Runs an admin command and prints its outcome, serving is left to `main`.
+pub fn run(command: Command, config: &Config) -> Result<(), CliError> {
+    match command {
+        Command::Serve => Err("serve is not an admin command".into()),
+        Command::Migrate => {
+            let (_, store) = store::from_config(config);
+            let applied = store.run_pending_migrations()?;
+
+            println!("Applied {} pending migrations {:?}", applied.len(), applied);
+            Ok(())
+        },
+        Command::Seed => {
+            let (_, store) = store::from_config(config);
+
+            println!("Seeded {} quotes", quotes::seed(store.as_ref())?);
+            Ok(())
+        },
+        Command::IssueToken { sub, scopes, ttl } => {
+            println!("{}", JwtSettings::from_config(config)?.sign(&sub, &scopes, ttl));
+            Ok(())
+        },
+        Command::CreateUser { username, scopes } => {
+            let pool = store::from_config(config).0.ok_or("Users are stored in Postgres only, set QUOTES_STORE=postgres")?;
+            let mut password = String::new();
+            std::io::stdin().read_line(&mut password)?;
+
+            let user = users::create_user(&pool, &username, password.trim_end_matches(['\r', '\n']), &scopes)?;
+            println!("Created user {} with id {}", user.username, user.id);
+            Ok(())
+        },
+        Command::Import { file } => {
+            let (_, store) = store::from_config(config);
+
+            println!("Imported {} quotes from {}", quotes::import(store.as_ref(), &file)?, file.display());
+            Ok(())
+        },
+        Command::Export { file } => {
+            let (_, store) = store::from_config(config);
+
+            println!("Exported {} quotes to {}", quotes::export(store.as_ref(), &file)?, file.display());
+            Ok(())
+        },
+        Command::CheckConfig => {
+            println!("Config: {}", config);
+            let jwt = JwtSettings::from_config(config)?;
+            if let Some(oidc) = OidcProvider::from_config(config, jwt.verifications)? {
+                println!("OIDC issuer {} reachable, verifying {:?}", oidc.issuer, oidc.kids());
+            }
+
+            let (_, store) = store::from_config(config);
+            let pending = store.pending_migrations()?;
+            if !pending.is_empty() {
+                return Err(format!("Pending migrations: {}", pending.join(", ")).into());
+            }
+
+            println!("Database reachable and up to date");
+            Ok(())
+        },
+    }
+}
+
+#[test]
+fn test_cli() {
+    use clap::CommandFactory;
+
+    Cli::command().debug_assert();
+
+    let cli = Cli::parse_from(["rust-playground", "issue-token", "--sub", "batch", "--scopes", "admin,quotes:read"]);
+    assert_eq!(cli.command, Some(Command::IssueToken {
+        sub: "batch".to_string(),
+        scopes: vec!["admin".to_string(), "quotes:read".to_string()],
+        ttl: 3600,
+    }));
+
+    let cli = Cli::parse_from(["rust-playground", "create-user", "--username", "haddock", "--scopes", "quotes:read"]);
+    assert_eq!(cli.command, Some(Command::CreateUser { username: "haddock".to_string(), scopes: vec!["quotes:read".to_string()] }));
+
+    assert_eq!(Cli::parse_from(["rust-playground"]).command, None);
+    assert!(Cli::try_parse_from(["rust-playground", "issue-token", "--sub", "batch", "--ttl", "0"]).is_err());
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::Path;
+use validator::Validate;
+use crate::db::entities::quote::{ApiPayloadQuote, ApiQuote};
+use crate::db::repositories::quote::{QuoteCursor, QuoteFilters, QuoteSort};
+use crate::db::store::QuoteStore;
+use crate::cli::CliError;
+
+const EXPORT_PAGE_SIZE: i64 = 500;
+
+/// Sample quotes for development and demo databases.
+const SEED_QUOTES: &str = include_str!("../../seeds/quotes.json");
+
+/// Validates every quote before creating any, so that a bad file creates nothing.
+fn create_all(store: &dyn QuoteStore, payloads: Vec<ApiPayloadQuote>) -> Result<usize, CliError> {
+    for (position, payload) in payloads.iter().enumerate() {
+        payload.validate().map_err(|err| format!("Quote {} is invalid: {}", position, err))?;
+    }
+
+    // Created by no one in particular
+    for payload in &payloads {
+        store.create_quote(payload, None)?;
+    }
+
+    Ok(payloads.len())
+}
+
+/// Creates the sample quotes unless the store already has quotes, returns how many were created.
+pub fn seed(store: &dyn QuoteStore) -> Result<usize, CliError> {
+    let any_filters = QuoteFilters { include_deleted: true, ..QuoteFilters::default() };
+    if !store.list_quotes(1, None, QuoteSort::default(), &any_filters)?.is_empty() {
+        return Ok(0);
+    }
+
+    create_all(store, serde_json::from_str(SEED_QUOTES)?)
+}
+
+/// Creates the quotes of the JSON file, their ids and timestamps are new ones.
+pub fn import(store: &dyn QuoteStore, path: &Path) -> Result<usize, CliError> {
+    let payloads: Vec<ApiPayloadQuote> = serde_json::from_slice(&fs::read(path)?)?;
+
+    create_all(store, payloads)
+}
+
+/// Writes the quotes that are not deleted, in id order, returns how many.
+pub fn export(store: &dyn QuoteStore, path: &Path) -> Result<usize, CliError> {
+    let sort = QuoteSort::default();
+    let mut exported: Vec<ApiQuote> = Vec::new();
+    let mut cursor = None;
+
+    loop {
+        let page = store.list_quotes(EXPORT_PAGE_SIZE, cursor, sort, &QuoteFilters::default())?;
+        let last_page = (page.len() as i64) < EXPORT_PAGE_SIZE;
+
+        cursor = page.last().map(|last_quote| QuoteCursor::after(&last_quote.quote, sort));
+        exported.extend(page);
+
+        if last_page {
+            break;
+        }
+    }
+
+    fs::write(path, serde_json::to_vec_pretty(&exported)?)?;
+
+    Ok(exported.len())
+}
+
+#[test]
+fn test_seed() {
+    use crate::db::store::memory::MemoryQuoteStore;
+
+    let store = MemoryQuoteStore::default();
+
+    let seeded = seed(&store).unwrap();
+    assert!(seeded > 0);
+    assert_eq!(store.list_quotes(100, None, QuoteSort::default(), &QuoteFilters::default()).unwrap().len(), seeded);
+
+    // Only an empty store is seeded
+    assert_eq!(seed(&store).unwrap(), 0);
+}
+
+#[test]
+fn test_import_export() {
+    use uuid::Uuid;
+    use crate::db::store::memory::MemoryQuoteStore;
+
+    let source = MemoryQuoteStore::default();
+    seed(&source).unwrap();
+
+    let path = std::env::temp_dir().join(format!("quotes-{}.json", Uuid::new_v4()));
+    let exported = export(&source, &path).unwrap();
+
+    let target = MemoryQuoteStore::default();
+    assert_eq!(import(&target, &path).unwrap(), exported);
+
+    let quotes = target.list_quotes(100, None, QuoteSort::default(), &QuoteFilters::default()).unwrap();
+    assert_eq!(quotes.len(), exported);
+    assert!(quotes.iter().any(|found| found.quote.author == "Capitaine Haddock" && found.tags == vec!["humour".to_string()]));
+
+    // Nothing is created when a quote is invalid
+    fs::write(&path, r#"[{"author": "Tryphon Tournesol", "quote": "Un peu plus a l'ouest"}, {"author": "Tintin", "quote": "Milou !"}]"#).unwrap();
+    assert!(import(&target, &path).is_err());
+    assert_eq!(target.list_quotes(100, None, QuoteSort::default(), &QuoteFilters::default()).unwrap().len(), exported);
+
+    fs::remove_file(path).ok();
+}
@@ -0,0 +1,30 @@
+use chrono::Utc;
+use uuid::Uuid;
+use crate::db::entities::user::{hash_password, User};
+use crate::db::pool::DbPool;
+use crate::db::repositories::user::UserRepository;
+use crate::cli::CliError;
+
+/// Minimum password length, longer passphrases are welcome.
+const MIN_PASSWORD_LENGTH: usize = 12;
+
+pub fn create_user(pool: &DbPool, username: &str, password: &str, scopes: &[String]) -> Result<User, CliError> {
+    if username.trim().is_empty() {
+        return Err("The username is empty".into());
+    }
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!("The password must have at least {} characters", MIN_PASSWORD_LENGTH).into());
+    }
+
+    let user = User {
+        id: Uuid::new_v4().to_string(),
+        username: username.trim().to_string(),
+        password_hash: hash_password(password).map_err(|err| err.to_string())?,
+        scope: scopes.join(" "),
+        created_at: Utc::now(),
+    };
+    let mut conn = pool.get()?;
+    UserRepository.insert(&user, &mut conn)?;
+
+    Ok(user)
+}
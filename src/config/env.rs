@@ -1,10 +1,51 @@
-use std::env;
+use std::{env, fmt};
 
 
 pub struct Config {
     pub log_level: String,
     pub database_url: String,
+    /// Applies the pending migrations at boot
+    pub database_auto_migrate: bool,
+    /// HS256 shared secret, optional when tokens are signed with JWT_SIGNING_KEY_FILE
     pub jwt_secret: String,
+    /// PKCS#8 private key PEM signing the issued tokens, published in the JWKS under JWT_SIGNING_KEY_ID
+    pub jwt_signing_key_file: String,
+    pub jwt_signing_key_id: String,
+    /// Comma separated `kid=path.pem` public keys verifying tokens of other issuers
+    pub jwt_public_key_files: String,
+    /// JWKS file of public keys verifying tokens of other issuers
+    pub jwt_jwks_file: String,
+    /// JSON keyring of a signing key and the keys still verifying, replaces JWT_SIGNING_KEY_FILE
+    pub jwt_keyring_file: String,
+    /// How often the keys are read again, so that they rotate without restart
+    pub jwt_keyring_reload_interval: usize,
+    /// How often the revoked tokens are read again, picking up the revocations of the other instances
+    pub jwt_revocation_refresh_interval: usize,
+    /// Required `iss` and `aud` of the bearer tokens, not checked when empty
+    pub jwt_issuer: String,
+    pub jwt_audience: String,
+    /// Clock skew tolerated on `exp` and `nbf`, in seconds
+    pub jwt_leeway: usize,
+
+    /// External OpenID Connect issuer whose access tokens are accepted too, disabled when empty
+    pub oidc_issuer: String,
+    /// URL or file of its discovery document, `OIDC_ISSUER/.well-known/openid-configuration` when empty
+    pub oidc_discovery_url: String,
+    /// Required `aud` of its tokens, not checked when empty
+    pub oidc_audience: String,
+    /// Dotted path of the claim granting the scopes, e.g. `realm_access.roles`
+    pub oidc_scope_claim: String,
+    /// How often its discovery document and keys are read again, picking up its key rotations
+    pub oidc_refresh_interval: usize,
+
+    /// Client exchanging its credentials for tokens at `POST /auth/token`, disabled when the secret is empty
+    pub auth_client_id: String,
+    pub auth_client_secret: String,
+    /// Space separated scopes granted to the client tokens
+    pub auth_client_scopes: String,
+    pub auth_token_ttl: usize,
+    /// Validity of the refresh tokens of the users, renewed by every exchange
+    pub auth_refresh_token_ttl: usize,
 
     pub http_server_max_connexion: usize,
     pub http_server_num_worker: usize,
@@ -15,14 +56,39 @@ pub struct Config {
 
     pub prometheus_metrics_path: String,
     pub prometheus_namespace: String,
+
+    pub quotes_retention_days: usize,
+    pub quotes_purge_interval: usize,
+    pub quotes_store: String,
 }
 
-impl Config {
-    pub fn to_string(&self) -> String {
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // We don't want to disclose the secret
-        format!(
-            "log_level={}, http_server_max_connexion={}, http_server_num_worker={}, http_server_hostname={}, http_listen_ip={}, http_listen_port={}, prometheus_metrics_path={}, prometheus_namespace={}",
+        write!(
+            f,
+            "log_level={}, database_auto_migrate={}, jwt_signing_key_file={}, jwt_signing_key_id={}, jwt_public_key_files={}, jwt_jwks_file={}, jwt_keyring_file={}, jwt_keyring_reload_interval={}, jwt_revocation_refresh_interval={}, jwt_issuer={}, jwt_audience={}, jwt_leeway={}, oidc_issuer={}, oidc_discovery_url={}, oidc_audience={}, oidc_scope_claim={}, oidc_refresh_interval={}, auth_client_id={}, auth_client_scopes={}, auth_token_ttl={}, auth_refresh_token_ttl={}, http_server_max_connexion={}, http_server_num_worker={}, http_server_hostname={}, http_listen_ip={}, http_listen_port={}, prometheus_metrics_path={}, prometheus_namespace={}, quotes_retention_days={}, quotes_purge_interval={}, quotes_store={}",
             &self.log_level,
+            &self.database_auto_migrate,
+            &self.jwt_signing_key_file,
+            &self.jwt_signing_key_id,
+            &self.jwt_public_key_files,
+            &self.jwt_jwks_file,
+            &self.jwt_keyring_file,
+            &self.jwt_keyring_reload_interval,
+            &self.jwt_revocation_refresh_interval,
+            &self.jwt_issuer,
+            &self.jwt_audience,
+            &self.jwt_leeway,
+            &self.oidc_issuer,
+            &self.oidc_discovery_url,
+            &self.oidc_audience,
+            &self.oidc_scope_claim,
+            &self.oidc_refresh_interval,
+            &self.auth_client_id,
+            &self.auth_client_scopes,
+            &self.auth_token_ttl,
+            &self.auth_refresh_token_ttl,
             &self.http_server_max_connexion,
             &self.http_server_num_worker,
             &self.http_server_hostname,
@@ -30,6 +96,9 @@ impl Config {
             &self.http_listen_port,
             &self.prometheus_metrics_path,
             &self.prometheus_namespace,
+            &self.quotes_retention_days,
+            &self.quotes_purge_interval,
+            &self.quotes_store,
         )
     }
 }
@@ -59,12 +128,44 @@ fn env_or_int(name: String, default : String) -> usize {
         .unwrap()
 }
 
+fn env_or_bool(name: String, default : String) -> bool {
+    env::var(name)
+        .unwrap_or(
+            default
+        )
+        .parse::<bool>()
+        .unwrap()
+}
+
 pub fn load_config_from_env () -> Config {
 
     Config {
         log_level: env_or_string("RUST_LOG".to_string(), "DEBUG".to_string()),
         database_url: env_or_panic("DATABASE_URL".to_string()),
-        jwt_secret: env_or_panic("JWT_SECRET".to_string()),
+        database_auto_migrate: env_or_bool("DATABASE_AUTO_MIGRATE".to_string(), "false".to_string()),
+        jwt_secret: env_or_string("JWT_SECRET".to_string(), "".to_string()),
+        jwt_signing_key_file: env_or_string("JWT_SIGNING_KEY_FILE".to_string(), "".to_string()),
+        jwt_signing_key_id: env_or_string("JWT_SIGNING_KEY_ID".to_string(), "".to_string()),
+        jwt_public_key_files: env_or_string("JWT_PUBLIC_KEY_FILES".to_string(), "".to_string()),
+        jwt_jwks_file: env_or_string("JWT_JWKS_FILE".to_string(), "".to_string()),
+        jwt_keyring_file: env_or_string("JWT_KEYRING_FILE".to_string(), "".to_string()),
+        jwt_keyring_reload_interval: env_or_int("JWT_KEYRING_RELOAD_INTERVAL_SECONDS".to_string(), "60".to_string()),
+        jwt_revocation_refresh_interval: env_or_int("JWT_REVOCATION_REFRESH_INTERVAL_SECONDS".to_string(), "30".to_string()),
+        jwt_issuer: env_or_string("JWT_ISSUER".to_string(), "rust-playground".to_string()),
+        jwt_audience: env_or_string("JWT_AUDIENCE".to_string(), "rust-playground".to_string()),
+        jwt_leeway: env_or_int("JWT_LEEWAY_SECONDS".to_string(), "60".to_string()),
+
+        oidc_issuer: env_or_string("OIDC_ISSUER".to_string(), "".to_string()),
+        oidc_discovery_url: env_or_string("OIDC_DISCOVERY_URL".to_string(), "".to_string()),
+        oidc_audience: env_or_string("OIDC_AUDIENCE".to_string(), "".to_string()),
+        oidc_scope_claim: env_or_string("OIDC_SCOPE_CLAIM".to_string(), "scope".to_string()),
+        oidc_refresh_interval: env_or_int("OIDC_REFRESH_INTERVAL_SECONDS".to_string(), "300".to_string()),
+
+        auth_client_id: env_or_string("AUTH_CLIENT_ID".to_string(), "".to_string()),
+        auth_client_secret: env_or_string("AUTH_CLIENT_SECRET".to_string(), "".to_string()),
+        auth_client_scopes: env_or_string("AUTH_CLIENT_SCOPES".to_string(), "".to_string()),
+        auth_token_ttl: env_or_int("AUTH_TOKEN_TTL_SECONDS".to_string(), "900".to_string()),
+        auth_refresh_token_ttl: env_or_int("AUTH_REFRESH_TOKEN_TTL_SECONDS".to_string(), "1209600".to_string()),
 
         http_server_max_connexion: env_or_int("HTTP_SERVER_MAX_CONNEXION".to_string(), "5".to_string()),
         http_server_num_worker: env_or_int("HTTP_SERVER_NUM_WORKERS".to_string(), "5".to_string()),
@@ -75,5 +176,9 @@ pub fn load_config_from_env () -> Config {
 
         prometheus_metrics_path: env_or_string("PROMETHEUS_METRICS_PATH".to_string(), "/metrics".to_string()),
         prometheus_namespace: env_or_string("PROMETHEUS_NAMESPACE".to_string(), "rust-playground".to_string()),
+
+        quotes_retention_days: env_or_int("QUOTES_RETENTION_DAYS".to_string(), "30".to_string()),
+        quotes_purge_interval: env_or_int("QUOTES_PURGE_INTERVAL_SECONDS".to_string(), "3600".to_string()),
+        quotes_store: env_or_string("QUOTES_STORE".to_string(), "postgres".to_string()),
     }
 }
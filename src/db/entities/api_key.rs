@@ -0,0 +1,86 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use validator::Validate;
+use utoipa::ToSchema;
+
+use crate::db::schema::api_keys;
+
+/// Prefix of the generated keys, so that leaked keys are easy to spot.
+const KEY_PREFIX: &str = "rpk_";
+
+/// Credential of the clients which cannot mint JWTs, sent in the `X-Api-Key` header.
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Insertable, Clone, ToSchema)]
+#[diesel(table_name = api_keys)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    /// Who the key was issued to, the requests made with it have the `apikey:<id>` subject
+    pub owner: String,
+    #[serde(skip)]
+    pub key_hash: String,
+    /// Space separated scopes
+    #[schema(example = "quotes:read")]
+    pub scope: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Validate, Deserialize, Serialize, ToSchema)]
+pub struct ApiPayloadApiKey {
+    #[validate(length(min = 1))]
+    #[schema(example = "nightly import")]
+    pub name: String,
+    #[validate(length(min = 1))]
+    #[schema(example = "batch-import")]
+    pub owner: String,
+    /// Space separated scopes
+    #[schema(example = "quotes:read quotes:write")]
+    pub scope: String,
+    /// Never expires when omitted
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Created key, the only response holding the key itself.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ApiCreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    #[schema(example = "rpk_0X3Hkgd6CQ8z4_9KcBvVqI9lJvx4hIp0G5vb0yYB1Ss")]
+    pub key: String,
+}
+
+/// New random key, 256 bits so that a plain hash is enough to store it.
+pub fn generate_key() -> String {
+    random_secret(KEY_PREFIX)
+}
+
+/// 256 random bits after the prefix, stored as their `hash_key`.
+pub fn random_secret(prefix: &str) -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    format!("{}{}", prefix, URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Hex SHA-256 of the key, what is stored and looked up.
+pub fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[test]
+fn test_generate_key() {
+    let key = generate_key();
+
+    assert!(key.starts_with(KEY_PREFIX));
+    assert_ne!(key, generate_key());
+    assert_eq!(hash_key(&key), hash_key(&key));
+    assert_eq!(hash_key("abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+}
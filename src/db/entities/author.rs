@@ -0,0 +1,84 @@
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use validator::{Validate, ValidationError};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::db::schema::authors;
+
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Insertable, Clone, AsChangeset, ToSchema)]
+#[diesel(table_name = authors)]
+#[diesel(treat_none_as_null = true)]
+pub struct Author {
+    pub id: String,
+    pub name: String,
+    pub slug: String,
+    pub bio: Option<String>,
+    pub birth_date: Option<NaiveDate>,
+    pub death_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Validate, Deserialize, Serialize, ToSchema)]
+pub struct ApiPayloadAuthor {
+    #[validate(length(min = 1), custom = "validate_slug")]
+    pub name: String,
+    pub bio: Option<String>,
+    pub birth_date: Option<NaiveDate>,
+    pub death_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApiAuthorListParams {
+    /// Maximum number of authors to return (default 100, max 1000)
+    pub limit: Option<i64>,
+    /// Cursor taken from the `next_cursor` of the previous page, the slug of its last author
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ApiAuthorPage {
+    pub items: Vec<Author>,
+    /// Cursor to fetch the next page, absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Normalized form of an author name, two names with the same slug are the same author.
+///
+/// Must stay in sync with the `slugify` SQL function used to backfill the authors table.
+pub fn slugify(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+/// Refuses names without any letter or digit, they would all share the empty slug.
+pub fn validate_slug(name: &str) -> Result<(), ValidationError> {
+    if slugify(name).is_empty() {
+        return Err(ValidationError::new("slug"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_slugify() {
+    assert_eq!(slugify("Foo"), slugify("foo "));
+    assert_eq!(slugify("  Jean-Claude   Van Damme! "), "jean-claude-van-damme");
+    assert_eq!(slugify(",nwxcnnx"), "nwxcnnx");
+    assert_eq!(slugify("Émile Zola"), "émile-zola");
+    assert_eq!(slugify("Лев Толстой"), "лев-толстой");
+    assert_eq!(slugify("孔子"), "孔子");
+    assert_eq!(slugify(" !?. "), "");
+}
+
+#[test]
+fn test_validate_slug() {
+    assert!(validate_slug("孔子").is_ok());
+    assert!(validate_slug("—").is_err());
+
+    let payload = ApiPayloadAuthor { name: "?!".to_string(), bio: None, birth_date: None, death_date: None };
+    assert!(payload.validate().is_err());
+}
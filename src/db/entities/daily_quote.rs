@@ -0,0 +1,22 @@
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use validator::Validate;
+use utoipa::ToSchema;
+
+use crate::db::schema::daily_quotes;
+
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Insertable, Clone, AsChangeset, ToSchema)]
+#[diesel(table_name = daily_quotes)]
+pub struct DailyQuote {
+    pub day: NaiveDate,
+    pub quote_id: String,
+    pub pinned: bool,
+}
+
+#[derive(Debug, Validate, Deserialize, Serialize, ToSchema)]
+pub struct ApiPayloadDailyQuote {
+    #[validate(length(min = 1))]
+    pub quote_id: String,
+    /// UTC day to pin the quote on, today when omitted
+    pub day: Option<NaiveDate>,
+}
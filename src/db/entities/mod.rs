@@ -1 +1,9 @@
 pub mod quote;
+pub mod daily_quote;
+pub mod author;
+pub mod tag;
+pub mod quote_revision;
+pub mod revoked_token;
+pub mod api_key;
+pub mod user;
+pub mod refresh_token;
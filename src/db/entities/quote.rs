@@ -1,21 +1,126 @@
+use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
-use validator::Validate;
-use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+use utoipa::{IntoParams, ToSchema};
 
+use crate::db::entities::author::{slugify, validate_slug};
+use crate::db::entities::tag::TagMode;
 use crate::db::schema::quotes;
 
-#[derive(Serialize, Deserialize, Queryable, Debug, Insertable, Clone, AsChangeset, ToSchema)]
+#[derive(Serialize, Deserialize, Queryable, QueryableByName, Selectable, Debug, Insertable, Clone, ToSchema)]
 #[diesel(table_name = quotes)]
 pub struct Quote {
     pub id: String,
+    /// Name of the author, kept in sync with the referenced author
     pub author: String,
     pub quote: String,
+    pub author_id: String,
+    pub created_at: DateTime<Utc>,
+    /// Bumped by the database on every change of the row
+    pub updated_at: DateTime<Utc>,
+    /// Set when the quote was deleted, it is purged after the retention period
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Incremented on every change, used for optimistic concurrency through the ETag
+    pub version: i32,
+    /// Subject of the credentials the quote was created with
+    pub created_by: Option<String>,
+}
+
+/// Quote as returned by the API, with its tags.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ApiQuote {
+    #[serde(flatten)]
+    pub quote: Quote,
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Validate, Deserialize, Serialize, ToSchema)]
 pub struct ApiPayloadQuote {
-    #[validate(length(min = 10))]
+    #[validate(length(min = 10), custom = "validate_slug")]
     pub author: String,
     #[validate(length(min = 5))]
     pub quote: String,
+    /// Replaces the tags of the quote, left untouched when omitted
+    #[validate(custom = "validate_tags")]
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+}
+
+fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    if tags.iter().any(|tag| tag.len() > 50 || slugify(tag).is_empty()) {
+        return Err(ValidationError::new("tag"));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApiQuoteListParams {
+    /// Maximum number of quotes to return (default 100, max 1000)
+    pub limit: Option<i64>,
+    /// Opaque cursor taken from the `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// Whether quotes must have any (default) or all of the `tag` parameters
+    #[param(inline)]
+    pub tag_mode: Option<TagMode>,
+    /// Field to sort on, `id` by default
+    #[param(inline)]
+    pub sort: Option<QuoteSortField>,
+    /// Sort direction, ascending by default
+    #[param(inline)]
+    pub order: Option<SortOrder>,
+    /// Only list quotes created or modified at or after this instant (RFC 3339)
+    pub updated_since: Option<DateTime<Utc>>,
+    /// Also list deleted quotes, admin only
+    pub include_deleted: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApiQuoteItemParams {
+    /// Also return the quote when it is deleted, admin only
+    pub include_deleted: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QuoteSortField {
+    #[default]
+    Id,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ApiQuotePage {
+    pub items: Vec<ApiQuote>,
+    /// Cursor to fetch the next page, absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApiQuoteSearchParams {
+    /// Search terms, supports the web search syntax ("quoted phrases", -excluded, or)
+    pub q: String,
+    /// Maximum number of quotes to return (default 20, max 100)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApiQuoteRandomParams {
+    /// Only pick among the quotes of this author, matched on the normalized name
+    pub author: Option<String>,
+    /// Only pick among the quotes having this tag
+    pub tag: Option<String>,
 }
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::db::schema::quote_revisions;
+
+/// Content of a quote after one of its changes, revisions are numbered from 1.
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Insertable, Clone, ToSchema)]
+#[diesel(table_name = quote_revisions)]
+pub struct QuoteRevision {
+    pub quote_id: String,
+    pub revision: i32,
+    pub author: String,
+    pub author_id: String,
+    pub quote: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApiRevisionDiffParams {
+    /// Revision to compare from
+    pub from: i32,
+    /// Revision to compare to
+    pub to: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct ApiFieldChange {
+    pub field: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ApiRevisionDiff {
+    pub quote_id: String,
+    pub from: i32,
+    pub to: i32,
+    /// Fields whose value differs between the two revisions
+    pub changes: Vec<ApiFieldChange>,
+}
+
+impl QuoteRevision {
+    /// Whether both revisions hold the same content, regardless of when they were made.
+    pub fn same_content(&self, other: &QuoteRevision) -> bool {
+        self.diff(other).changes.is_empty()
+    }
+
+    pub fn diff(&self, other: &QuoteRevision) -> ApiRevisionDiff {
+        let fields = [
+            ("author", &self.author, &other.author),
+            ("author_id", &self.author_id, &other.author_id),
+            ("quote", &self.quote, &other.quote),
+        ];
+
+        ApiRevisionDiff {
+            quote_id: self.quote_id.clone(),
+            from: self.revision,
+            to: other.revision,
+            changes: fields
+                .into_iter()
+                .filter(|(_, from, to)| from != to)
+                .map(|(field, from, to)| ApiFieldChange {
+                    field: field.to_string(),
+                    from: from.to_string(),
+                    to: to.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[test]
+fn test_diff() {
+    let first = QuoteRevision {
+        quote_id: "22e78eeb-3729-431e-aa80-60aea434088e".to_string(),
+        revision: 1,
+        author: "Foo".to_string(),
+        author_id: "1".to_string(),
+        quote: "Haaaaaaaa".to_string(),
+        created_at: Utc::now(),
+    };
+    let second = QuoteRevision { revision: 2, quote: "Haaaaaaaaaa".to_string(), ..first.clone() };
+
+    let diff = first.diff(&second);
+
+    assert_eq!((diff.from, diff.to), (1, 2));
+    assert_eq!(diff.changes, vec![ApiFieldChange {
+        field: "quote".to_string(),
+        from: "Haaaaaaaa".to_string(),
+        to: "Haaaaaaaaaa".to_string(),
+    }]);
+    assert!(first.same_content(&QuoteRevision { revision: 3, ..first.clone() }));
+}
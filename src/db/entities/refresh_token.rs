@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+
+use crate::db::schema::refresh_tokens;
+
+/// Single use token exchanged at `POST /auth/refresh` for an access token and the next refresh token.
+#[derive(Queryable, Selectable, Debug, Insertable, Clone)]
+#[diesel(table_name = refresh_tokens)]
+pub struct RefreshToken {
+    pub id: String,
+    pub token_hash: String,
+    pub user_id: String,
+    /// Shared by the tokens rotated from the same login
+    pub family_id: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Set once exchanged, exchanging it again revokes the family
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
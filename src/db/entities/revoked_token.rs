@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::db::schema::revoked_tokens;
+
+/// Bearer token refused until it expires, identified by its `jti` claim.
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Insertable, Clone, PartialEq)]
+#[diesel(table_name = revoked_tokens)]
+pub struct RevokedToken {
+    pub jti: String,
+    pub subject: Option<String>,
+    /// `exp` of the token, the entry is pruned afterwards
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: DateTime<Utc>,
+}
@@ -0,0 +1,30 @@
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+
+use crate::db::schema::{quote_tags, tags};
+
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Insertable, Clone, ToSchema)]
+#[diesel(table_name = tags)]
+pub struct Tag {
+    pub id: String,
+    /// Normalized tag name, see `db::entities::author::slugify`
+    pub name: String,
+}
+
+#[derive(Queryable, Selectable, Debug, Insertable, Clone)]
+#[diesel(table_name = quote_tags)]
+pub struct QuoteTag {
+    pub quote_id: String,
+    pub tag_id: String,
+}
+
+/// How several tags filter a quote listing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMode {
+    /// Quotes having at least one of the tags
+    #[default]
+    Any,
+    /// Quotes having every tag
+    All,
+}
@@ -0,0 +1,44 @@
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use argon2::password_hash::{self, SaltString, rand_core::OsRng};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+
+use crate::db::schema::users;
+
+/// Account logging in with a password at `POST /auth/login`, its id is the subject of its tokens.
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Insertable, Clone, ToSchema)]
+#[diesel(table_name = users)]
+pub struct User {
+    pub id: String,
+    pub username: String,
+    #[serde(skip)]
+    pub password_hash: String,
+    /// Space separated scopes granted to the user tokens
+    #[schema(example = "quotes:read quotes:write")]
+    pub scope: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Argon2id PHC string of the password, with a random salt.
+pub fn hash_password(password: &str) -> Result<String, password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Ok(Argon2::default().hash_password(password.as_bytes(), &salt)?.to_string())
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .is_ok_and(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+}
+
+#[test]
+fn test_hash_password() {
+    let hashed = hash_password("Tonnerre de Brest").unwrap();
+
+    assert!(hashed.starts_with("$argon2id$"));
+    assert_ne!(hashed, hash_password("Tonnerre de Brest").unwrap());
+    assert!(verify_password("Tonnerre de Brest", &hashed));
+    assert!(!verify_password("tonnerre de brest", &hashed));
+    assert!(!verify_password("Tonnerre de Brest", "not a hash"));
+}
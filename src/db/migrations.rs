@@ -0,0 +1,32 @@
+use diesel::backend::Backend;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+/// Postgres migrations, built into the binary so that setting up the database needs no diesel CLI.
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+#[cfg(feature = "sqlite")]
+pub const SQLITE_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations_sqlite");
+
+pub type MigrationError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Names of the migrations not applied yet to the database.
+pub fn pending<DB: Backend>(connection: &mut impl MigrationHarness<DB>, migrations: EmbeddedMigrations) -> Result<Vec<String>, MigrationError> {
+    Ok(
+        connection
+            .pending_migrations(migrations)?
+            .iter()
+            .map(|migration| migration.name().to_string())
+            .collect()
+    )
+}
+
+/// Applies the pending migrations, each in its own transaction, returns their versions.
+pub fn run_pending<DB: Backend>(connection: &mut impl MigrationHarness<DB>, migrations: EmbeddedMigrations) -> Result<Vec<String>, MigrationError> {
+    Ok(
+        connection
+            .run_pending_migrations(migrations)?
+            .iter()
+            .map(|version| version.to_string())
+            .collect()
+    )
+}
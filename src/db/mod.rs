@@ -1,4 +1,8 @@
 pub mod entities;
 pub mod repositories;
 pub mod schema;
+#[cfg(feature = "sqlite")]
+pub mod schema_sqlite;
 pub mod pool;
+pub mod migrations;
+pub mod store;
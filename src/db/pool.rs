@@ -9,3 +9,33 @@ pub fn build_db_pool (database_url: String) -> Pool<ConnectionManager<PgConnecti
         .build(manager)
         .expect("database URL should be valid")
 }
+
+#[cfg(feature = "sqlite")]
+pub type SqlitePool = diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<diesel::SqliteConnection>>;
+
+/// Settings SQLite keeps per connection, applied to each one the pool opens.
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+struct SqlitePragmas;
+
+#[cfg(feature = "sqlite")]
+impl diesel::r2d2::CustomizeConnection<diesel::SqliteConnection, diesel::r2d2::Error> for SqlitePragmas {
+    fn on_acquire(&self, connection: &mut diesel::SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        use diesel::connection::SimpleConnection;
+
+        // Cascading deletes need the foreign keys, concurrent writers wait for the lock instead of failing
+        connection
+            .batch_execute("PRAGMA foreign_keys = ON; PRAGMA busy_timeout = 5000; PRAGMA journal_mode = WAL;")
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+/// Pool over the SQLite database file at `database_url`, created when missing.
+#[cfg(feature = "sqlite")]
+pub fn build_sqlite_pool (database_url: String) -> SqlitePool {
+    let manager = diesel::r2d2::ConnectionManager::<diesel::SqliteConnection>::new(database_url);
+    diesel::r2d2::Pool::builder()
+        .connection_customizer(Box::new(SqlitePragmas))
+        .build(manager)
+        .expect("database URL should be valid")
+}
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use crate::db::entities::api_key::ApiKey;
+use crate::db::schema::api_keys::dsl::*;
+
+/// `last_used_at` is written at most once per interval, not on every request.
+const LAST_USED_PRECISION_SECONDS: i64 = 60;
+
+pub struct ApiKeyRepository;
+
+impl ApiKeyRepository {
+    pub fn insert(&self, api_key: &ApiKey, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::insert_into(api_keys)
+            .values(api_key)
+            .execute(connection)
+    }
+
+    /// Every key, revoked ones included, newest first.
+    pub fn get_api_keys(&self, connection: &mut PgConnection) -> QueryResult<Vec<ApiKey>> {
+        api_keys
+            .select(ApiKey::as_select())
+            .order(created_at.desc())
+            .load(connection)
+    }
+
+    /// Key with the given hash, unless revoked or expired at `now`.
+    pub fn get_active_by_hash(&self, other_hash: &str, now: DateTime<Utc>, connection: &mut PgConnection) -> QueryResult<ApiKey> {
+        api_keys
+            .filter(key_hash.eq(other_hash))
+            .filter(revoked_at.is_null())
+            .filter(expires_at.is_null().or(expires_at.gt(now)))
+            .select(ApiKey::as_select())
+            .first(connection)
+    }
+
+    pub fn touch(&self, other_id: &str, now: DateTime<Utc>, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::update(
+            api_keys
+                .find(other_id)
+                .filter(last_used_at.is_null().or(last_used_at.lt(now - Duration::seconds(LAST_USED_PRECISION_SECONDS))))
+        )
+            .set(last_used_at.eq(now))
+            .execute(connection)
+    }
+
+    /// Revokes the key unless already revoked, returns how many keys were revoked.
+    pub fn revoke(&self, other_id: String, now: DateTime<Utc>, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::update(api_keys.find(other_id).filter(revoked_at.is_null()))
+            .set(revoked_at.eq(now))
+            .execute(connection)
+    }
+}
@@ -0,0 +1,125 @@
+use diesel::prelude::*;
+use uuid::Uuid;
+use crate::db::entities::author::{Author, slugify};
+use crate::db::entities::quote::Quote;
+use crate::db::repositories::quote_revision::QuoteRevisionRepository;
+use crate::db::schema::authors::dsl::*;
+use crate::db::schema::quotes;
+
+pub struct AuthorRepository;
+
+impl AuthorRepository {
+    pub fn get_authors(&self, limit: Option<i64>, cursor: Option<String>, connection: &mut PgConnection) -> QueryResult<Vec<Author>> {
+        let mut query = authors
+            .select(Author::as_select())
+            .order(slug.asc())
+            .limit(limit.unwrap_or(10))
+            .into_boxed();
+
+        if let Some(after_slug) = cursor {
+            query = query.filter(slug.gt(after_slug));
+        }
+
+        query.load(connection)
+    }
+
+    pub fn get_author(&self, other_id: String, connection: &mut PgConnection) -> QueryResult<Author> {
+        authors
+            .find(other_id)
+            .select(Author::as_select())
+            .first(connection)
+    }
+
+    pub fn get_author_by_slug(&self, other_slug: String, connection: &mut PgConnection) -> QueryResult<Author> {
+        authors
+            .filter(slug.eq(other_slug))
+            .select(Author::as_select())
+            .first(connection)
+    }
+
+    /// Author matching the normalized name, created on the fly when unknown.
+    pub fn find_or_create(&self, author_name: &str, connection: &mut PgConnection) -> QueryResult<Author> {
+        let author_slug = slugify(author_name);
+
+        diesel::insert_into(authors)
+            .values((
+                id.eq(Uuid::new_v4().to_string()),
+                name.eq(author_name.trim()),
+                slug.eq(&author_slug),
+            ))
+            .on_conflict(slug)
+            .do_nothing()
+            .execute(connection)?;
+
+        self.get_author_by_slug(author_slug, connection)
+    }
+
+    pub fn remove(&self, other_id: String, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::delete(
+            authors
+            .find(other_id)
+        ).execute(connection)
+    }
+
+    pub fn insert(&self, author_new: Author, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::insert_into(authors)
+            .values(&author_new)
+            .execute(connection)
+    }
+
+    /// Updates the author and the denormalized author name kept on its quotes, each renamed quote
+    /// gets a revision.
+    pub fn update(&self, author_new: Author, connection: &mut PgConnection) -> QueryResult<usize> {
+        connection.transaction(|connection| {
+            let updated = diesel::update(authors.find(author_new.id.clone()))
+                .set(&author_new)
+                .execute(connection)?;
+
+            let renamed: Vec<Quote> = diesel::update(
+                quotes::table
+                    .filter(quotes::author_id.eq(&author_new.id))
+                    .filter(quotes::author.ne(&author_new.name))
+            )
+                .set((
+                    quotes::author.eq(&author_new.name),
+                    quotes::version.eq(quotes::version + 1),
+                ))
+                .returning(Quote::as_returning())
+                .get_results(connection)?;
+
+            for quote in &renamed {
+                QuoteRevisionRepository.record(quote, connection)?;
+            }
+
+            Ok(updated)
+        })
+    }
+}
+
+#[test]
+fn test_sql_slugify() {
+    use diesel::sql_types::Text;
+    use dotenv::dotenv;
+
+    #[derive(QueryableByName)]
+    struct Slugified {
+        #[diesel(sql_type = Text)]
+        value: String,
+    }
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let mut conn = pool.get().unwrap();
+
+    // The migrations backfilled the authors with the SQL function, new ones get the Rust slug
+    for author_name in ["  Jean-Claude   Van Damme! ", "Émile Zola", "ÉMILE ZOLA", "Лев Толстой", "孔子", "Ἀριστοτέλης", "R2-D2 & C-3PO", " !?. "] {
+        let found: Slugified = diesel::sql_query("SELECT slugify($1) AS value")
+            .bind::<Text, _>(author_name)
+            .get_result(&mut conn)
+            .unwrap();
+
+        assert_eq!(found.value, slugify(author_name), "{}", author_name);
+    }
+}
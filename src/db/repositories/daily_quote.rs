@@ -0,0 +1,63 @@
+use chrono::{Datelike, NaiveDate};
+use diesel::prelude::*;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use crate::db::entities::daily_quote::DailyQuote;
+use crate::db::entities::quote::Quote;
+use crate::db::repositories::quote::{QuoteFilters, QuoteRepository};
+use crate::db::schema::daily_quotes::dsl::*;
+
+pub struct DailyQuoteRepository;
+
+impl DailyQuoteRepository {
+    pub fn get_for_day(&self, other_day: NaiveDate, connection: &mut PgConnection) -> QueryResult<Option<DailyQuote>> {
+        daily_quotes
+            .find(other_day)
+            .select(DailyQuote::as_select())
+            .first(connection)
+            .optional()
+    }
+
+    /// Returns the quote of the given day, picking and storing one seeded by the date when
+    /// nothing was chosen yet so every caller gets the same quote for the whole day.
+    pub fn get_or_pick(&self, other_day: NaiveDate, connection: &mut PgConnection) -> QueryResult<Quote> {
+        let quote_repository = QuoteRepository;
+
+        if let Some(daily_quote) = self.get_for_day(other_day, connection)? {
+            match quote_repository.get_quote(daily_quote.quote_id, connection) {
+                // The quote was deleted since, pick another one
+                Err(diesel::result::Error::NotFound) => {
+                    diesel::delete(daily_quotes.find(other_day)).execute(connection)?;
+                },
+                found => return found,
+            }
+        }
+
+        let count = quote_repository.count_quotes(&QuoteFilters::default(), connection)?;
+        if count == 0 {
+            return Err(diesel::result::Error::NotFound);
+        }
+
+        let mut rng = StdRng::seed_from_u64(other_day.num_days_from_ce() as u64);
+        let picked = quote_repository.get_quote_at(rng.gen_range(0..count), &QuoteFilters::default(), connection)?;
+
+        diesel::insert_into(daily_quotes)
+            .values(DailyQuote { day: other_day, quote_id: picked.id.clone(), pinned: false })
+            .on_conflict_do_nothing()
+            .execute(connection)?;
+
+        // Another caller may have stored its pick (or an admin pinned one) in the meantime
+        match self.get_for_day(other_day, connection)? {
+            Some(daily_quote) if daily_quote.quote_id != picked.id => quote_repository.get_quote(daily_quote.quote_id, connection),
+            _ => Ok(picked),
+        }
+    }
+
+    pub fn pin(&self, daily_quote: DailyQuote, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::insert_into(daily_quotes)
+            .values(&daily_quote)
+            .on_conflict(day)
+            .do_update()
+            .set(&daily_quote)
+            .execute(connection)
+    }
+}
@@ -1,41 +1,313 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, SecondsFormat, Utc};
+use diesel::pg::Pg;
 use diesel::prelude::*;
-use crate::db::entities::quote::Quote;
+use diesel::sql_types::{BigInt, Bool, Text};
+use crate::db::entities::author::slugify;
+use crate::db::entities::quote::{Quote, QuoteSortField, SortOrder};
+use crate::db::entities::tag::TagMode;
+use crate::db::repositories::quote_revision::QuoteRevisionRepository;
+use crate::db::schema::{authors, quote_tags, tags};
 use crate::db::schema::quotes::dsl::*;
 
+/// Optional criteria narrowing down the quotes a query looks at.
+#[derive(Debug, Default, Clone)]
+pub struct QuoteFilters {
+    /// Author name, matched on its normalized form
+    pub author: Option<String>,
+    pub tags: Vec<String>,
+    pub tag_mode: TagMode,
+    pub updated_since: Option<DateTime<Utc>>,
+    /// Deleted quotes are hidden unless set
+    pub include_deleted: bool,
+}
+
+type QuoteCondition = Box<dyn BoxableExpression<quotes, Pg, SqlType = Bool>>;
+
+impl QuoteFilters {
+    fn condition(&self) -> QuoteCondition {
+        let mut condition: QuoteCondition = Box::new(true.into_sql::<Bool>());
+
+        if let Some(author_name) = &self.author {
+            condition = Box::new(condition.and(author_id.eq_any(
+                authors::table
+                    .filter(authors::slug.eq(slugify(author_name)))
+                    .select(authors::id)
+            )));
+        }
+
+        if !self.tags.is_empty() {
+            let tag_names: Vec<String> = self.tags.iter().map(|tag| slugify(tag)).collect();
+            let tagged = quote_tags::table
+                .inner_join(tags::table)
+                .filter(tags::name.eq_any(tag_names.clone()))
+                .select(quote_tags::quote_id);
+
+            condition = match self.tag_mode {
+                TagMode::Any => Box::new(condition.and(id.eq_any(tagged))),
+                TagMode::All => {
+                    let mut distinct_names = tag_names;
+                    distinct_names.sort();
+                    distinct_names.dedup();
+
+                    Box::new(condition.and(id.eq_any(
+                        tagged
+                            .group_by(quote_tags::quote_id)
+                            .having(diesel::dsl::count(quote_tags::tag_id).eq(distinct_names.len() as i64))
+                    )))
+                },
+            };
+        }
+
+        if let Some(since) = self.updated_since {
+            condition = Box::new(condition.and(updated_at.ge(since)));
+        }
+
+        if !self.include_deleted {
+            condition = Box::new(condition.and(deleted_at.is_null()));
+        }
+
+        condition
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct QuoteSort {
+    pub field: QuoteSortField,
+    pub order: SortOrder,
+}
+
+/// Position of the last quote of a page, pages are keyed on the sort field then the id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteCursor {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub id: String,
+}
+
+impl QuoteCursor {
+    pub fn after(last_quote: &Quote, sort: QuoteSort) -> QuoteCursor {
+        let timestamp = match sort.field {
+            QuoteSortField::Id => None,
+            QuoteSortField::CreatedAt => Some(last_quote.created_at),
+            QuoteSortField::UpdatedAt => Some(last_quote.updated_at),
+        };
+
+        QuoteCursor { timestamp, id: last_quote.id.clone() }
+    }
+
+    /// Parses a cursor previously produced by `encode`, `None` when malformed.
+    pub fn decode(value: &str) -> Option<QuoteCursor> {
+        let value = String::from_utf8(URL_SAFE_NO_PAD.decode(value).ok()?).ok()?;
+
+        match value.split_once(',') {
+            Some((timestamp, other_id)) => Some(QuoteCursor {
+                timestamp: Some(DateTime::parse_from_rfc3339(timestamp).ok()?.with_timezone(&Utc)),
+                id: other_id.to_string(),
+            }),
+            None => Some(QuoteCursor { timestamp: None, id: value }),
+        }
+    }
+
+    /// Base64url of the sort key and the id, so that clients treat it as opaque.
+    pub fn encode(&self) -> String {
+        let value = match self.timestamp {
+            Some(timestamp) => format!("{},{}", timestamp.to_rfc3339_opts(SecondsFormat::Micros, true), self.id),
+            None => self.id.clone(),
+        };
+
+        URL_SAFE_NO_PAD.encode(value)
+    }
+}
+
+#[test]
+fn test_cursor_round_trip() {
+    let cursor = QuoteCursor {
+        timestamp: DateTime::parse_from_rfc3339("2023-10-11T12:58:38.123456Z").ok().map(|date| date.with_timezone(&Utc)),
+        id: "22e78eeb-3729-431e-aa80-60aea434088e".to_string(),
+    };
+
+    assert_eq!(QuoteCursor::decode(&cursor.encode()), Some(cursor.clone()));
+
+    let by_id = QuoteCursor { timestamp: None, ..cursor };
+    assert!(!by_id.encode().contains(&by_id.id));
+    assert_eq!(QuoteCursor::decode(&by_id.encode()), Some(by_id));
+
+    assert_eq!(QuoteCursor::decode(&URL_SAFE_NO_PAD.encode("not a date,id")), None);
+    assert_eq!(QuoteCursor::decode("not base64!"), None);
+}
+
 pub struct QuoteRepository;
 
 impl QuoteRepository {
-    pub fn get_quotes(&self, limit: Option<i64>, connection: &mut PgConnection) -> QueryResult<Vec<Quote>> {
-        quotes
+    pub fn get_quotes(&self, limit: Option<i64>, cursor: Option<QuoteCursor>, sort: QuoteSort, filters: &QuoteFilters, connection: &mut PgConnection) -> QueryResult<Vec<Quote>> {
+        let mut query = quotes
+            .select(Quote::as_select())
+            .filter(filters.condition())
             .limit(limit.unwrap_or(10))
-            .load( connection)
+            .into_boxed();
+
+        query = match (sort.field, sort.order) {
+            (QuoteSortField::Id, SortOrder::Asc) => query.order(id.asc()),
+            (QuoteSortField::Id, SortOrder::Desc) => query.order(id.desc()),
+            (QuoteSortField::CreatedAt, SortOrder::Asc) => query.order((created_at.asc(), id.asc())),
+            (QuoteSortField::CreatedAt, SortOrder::Desc) => query.order((created_at.desc(), id.desc())),
+            (QuoteSortField::UpdatedAt, SortOrder::Asc) => query.order((updated_at.asc(), id.asc())),
+            (QuoteSortField::UpdatedAt, SortOrder::Desc) => query.order((updated_at.desc(), id.desc())),
+        };
+
+        if let Some(cursor) = cursor {
+            let timestamp = cursor.timestamp.unwrap_or_default();
+
+            query = match (sort.field, sort.order) {
+                (QuoteSortField::Id, SortOrder::Asc) => query.filter(id.gt(cursor.id)),
+                (QuoteSortField::Id, SortOrder::Desc) => query.filter(id.lt(cursor.id)),
+                (QuoteSortField::CreatedAt, SortOrder::Asc) => query.filter(
+                    created_at.gt(timestamp).or(created_at.eq(timestamp).and(id.gt(cursor.id)))
+                ),
+                (QuoteSortField::CreatedAt, SortOrder::Desc) => query.filter(
+                    created_at.lt(timestamp).or(created_at.eq(timestamp).and(id.lt(cursor.id)))
+                ),
+                (QuoteSortField::UpdatedAt, SortOrder::Asc) => query.filter(
+                    updated_at.gt(timestamp).or(updated_at.eq(timestamp).and(id.gt(cursor.id)))
+                ),
+                (QuoteSortField::UpdatedAt, SortOrder::Desc) => query.filter(
+                    updated_at.lt(timestamp).or(updated_at.eq(timestamp).and(id.lt(cursor.id)))
+                ),
+            };
+        }
+
+        query.load(connection)
     }
 
     pub fn get_quote(&self, other_id: String, connection: &mut PgConnection) -> QueryResult<Quote> {
 
         quotes
             .find(other_id)
+            .filter(deleted_at.is_null())
+            .select(Quote::as_select())
             .first(connection)
     }
 
-    pub fn remove(&self, other_id: String, connection: &mut PgConnection) -> QueryResult<usize> {
+    pub fn get_quote_including_deleted(&self, other_id: String, connection: &mut PgConnection) -> QueryResult<Quote> {
+        quotes
+            .find(other_id)
+            .select(Quote::as_select())
+            .first(connection)
+    }
 
-        diesel::delete(
+    pub fn count_quotes(&self, filters: &QuoteFilters, connection: &mut PgConnection) -> QueryResult<i64> {
+        quotes
+            .filter(filters.condition())
+            .count()
+            .get_result(connection)
+    }
+
+    /// Quote at the given position when ordered by id, used to draw quotes at random.
+    pub fn get_quote_at(&self, offset: i64, filters: &QuoteFilters, connection: &mut PgConnection) -> QueryResult<Quote> {
+        quotes
+            .select(Quote::as_select())
+            .filter(filters.condition())
+            .order(id.asc())
+            .offset(offset)
+            .first(connection)
+    }
+
+    pub fn search(&self, terms: String, limit: Option<i64>, connection: &mut PgConnection) -> QueryResult<Vec<Quote>> {
+        // Full text search on the generated `search_vector` column, quote text weighs more than the author
+        diesel::sql_query(
+            "SELECT quotes.* FROM quotes, websearch_to_tsquery('english', $1) AS search_query \
+            WHERE search_vector @@ search_query AND deleted_at IS NULL \
+            ORDER BY ts_rank(search_vector, search_query) DESC, id ASC \
+            LIMIT $2"
+        )
+            .bind::<Text, _>(terms)
+            .bind::<BigInt, _>(limit.unwrap_or(10))
+            .load(connection)
+    }
+
+    /// Soft deletes the quote, returns 0 when it does not exist, is already deleted or is not at
+    /// the expected version.
+    pub fn remove(&self, other_id: String, expected_version: Option<i32>, connection: &mut PgConnection) -> QueryResult<usize> {
+        let mut query = diesel::update(quotes)
+            .filter(id.eq(other_id))
+            .filter(deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(expected_version) = expected_version {
+            query = query.filter(version.eq(expected_version));
+        }
+
+        query
+            .set((
+                deleted_at.eq(diesel::dsl::now),
+                version.eq(version + 1),
+            ))
+            .execute(connection)
+    }
+
+    pub fn restore(&self, other_id: String, connection: &mut PgConnection) -> QueryResult<Quote> {
+        diesel::update(
             quotes
             .find(other_id)
+            .filter(deleted_at.is_not_null())
+        )
+            .set((
+                deleted_at.eq(None::<DateTime<Utc>>),
+                version.eq(version + 1),
+            ))
+            .returning(Quote::as_returning())
+            .get_result(connection)
+    }
+
+    /// Hard deletes the quotes soft deleted before the given instant.
+    pub fn purge_deleted(&self, deleted_before: DateTime<Utc>, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::delete(
+            quotes.filter(deleted_at.lt(deleted_before))
         ).execute(connection)
     }
 
-    pub fn insert(&self, quote_new: Quote, connection: &mut PgConnection) -> QueryResult<usize> {
+    /// Inserts the quote along with its first revision.
+    pub fn insert(&self, quote_new: Quote, connection: &mut PgConnection) -> QueryResult<Quote> {
+        connection.transaction(|connection| {
+            let inserted = diesel::insert_into(quotes)
+                .values(&quote_new)
+                .returning(Quote::as_returning())
+                .get_result(connection)?;
 
-        diesel::insert_into(quotes)
-            .values(&quote_new)
-            .execute(connection)
+            QuoteRevisionRepository.record(&inserted, connection)?;
+
+            Ok(inserted)
+        })
     }
 
-    pub fn update(&self, quote_new: Quote, connection: &mut PgConnection) -> QueryResult<usize> {
-        diesel::update(quotes)
-            .set(&quote_new)
-            .execute(connection)
+    /// Updates the quote content and records it as a new revision, `updated_at` is set by the
+    /// database trigger.
+    ///
+    /// With an expected version, fails with `NotFound` when the quote was changed in the meantime.
+    pub fn update(&self, quote_new: Quote, expected_version: Option<i32>, connection: &mut PgConnection) -> QueryResult<Quote> {
+        connection.transaction(|connection| {
+            let mut query = diesel::update(quotes)
+                .filter(id.eq(quote_new.id.clone()))
+                .into_boxed();
+
+            if let Some(expected_version) = expected_version {
+                query = query.filter(version.eq(expected_version));
+            }
+
+            let updated = query
+                .set((
+                    author.eq(quote_new.author),
+                    quote.eq(quote_new.quote),
+                    author_id.eq(quote_new.author_id),
+                    version.eq(version + 1),
+                ))
+                .returning(Quote::as_returning())
+                .get_result(connection)?;
+
+            QuoteRevisionRepository.record(&updated, connection)?;
+
+            Ok(updated)
+        })
     }
 }
@@ -0,0 +1,59 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use crate::db::entities::quote::Quote;
+use crate::db::entities::quote_revision::QuoteRevision;
+use crate::db::schema::quote_revisions::dsl::*;
+
+pub struct QuoteRevisionRepository;
+
+impl QuoteRevisionRepository {
+    pub fn get_revisions(&self, other_quote_id: String, connection: &mut PgConnection) -> QueryResult<Vec<QuoteRevision>> {
+        quote_revisions
+            .filter(quote_id.eq(other_quote_id))
+            .select(QuoteRevision::as_select())
+            .order(revision.asc())
+            .load(connection)
+    }
+
+    pub fn get_revision(&self, other_quote_id: String, other_revision: i32, connection: &mut PgConnection) -> QueryResult<QuoteRevision> {
+        quote_revisions
+            .find((other_quote_id, other_revision))
+            .select(QuoteRevision::as_select())
+            .first(connection)
+    }
+
+    pub fn get_latest_revision(&self, other_quote_id: String, connection: &mut PgConnection) -> QueryResult<Option<QuoteRevision>> {
+        quote_revisions
+            .filter(quote_id.eq(other_quote_id))
+            .select(QuoteRevision::as_select())
+            .order(revision.desc())
+            .first(connection)
+            .optional()
+    }
+
+    /// Records the current content of the quote as a new revision, unless it did not change.
+    ///
+    /// The revision is numbered after the version the write just set, so that concurrent writes,
+    /// serialized by the row lock of their update, never pick the same number.
+    pub fn record(&self, current: &Quote, connection: &mut PgConnection) -> QueryResult<Option<QuoteRevision>> {
+        let latest = self.get_latest_revision(current.id.clone(), connection)?;
+        let new_revision = QuoteRevision {
+            quote_id: current.id.clone(),
+            revision: current.version,
+            author: current.author.clone(),
+            author_id: current.author_id.clone(),
+            quote: current.quote.clone(),
+            created_at: Utc::now(),
+        };
+
+        if latest.is_some_and(|latest| latest.same_content(&new_revision)) {
+            return Ok(None);
+        }
+
+        diesel::insert_into(quote_revisions)
+            .values(&new_revision)
+            .returning(QuoteRevision::as_returning())
+            .get_result(connection)
+            .map(Some)
+    }
+}
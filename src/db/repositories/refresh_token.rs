@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use crate::db::entities::refresh_token::RefreshToken;
+use crate::db::schema::refresh_tokens::dsl::*;
+
+pub struct RefreshTokenRepository;
+
+impl RefreshTokenRepository {
+    pub fn insert(&self, refresh_token: &RefreshToken, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::insert_into(refresh_tokens)
+            .values(refresh_token)
+            .execute(connection)
+    }
+
+    /// Token with the given hash, used or revoked ones included.
+    pub fn get_by_hash(&self, other_hash: &str, connection: &mut PgConnection) -> QueryResult<RefreshToken> {
+        refresh_tokens
+            .filter(token_hash.eq(other_hash))
+            .select(RefreshToken::as_select())
+            .first(connection)
+    }
+
+    /// Marks the token used unless it already was or got revoked, returns how many tokens were marked.
+    pub fn mark_used(&self, other_id: &str, now: DateTime<Utc>, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::update(
+            refresh_tokens
+                .find(other_id)
+                .filter(used_at.is_null())
+                .filter(revoked_at.is_null())
+        )
+            .set(used_at.eq(now))
+            .execute(connection)
+    }
+
+    pub fn revoke_family(&self, other_family_id: &str, now: DateTime<Utc>, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::update(
+            refresh_tokens
+                .filter(family_id.eq(other_family_id))
+                .filter(revoked_at.is_null())
+        )
+            .set(revoked_at.eq(now))
+            .execute(connection)
+    }
+}
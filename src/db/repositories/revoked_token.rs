@@ -0,0 +1,31 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use crate::db::entities::revoked_token::RevokedToken;
+use crate::db::schema::revoked_tokens::dsl::*;
+
+pub struct RevokedTokenRepository;
+
+impl RevokedTokenRepository {
+    /// Revoking a token twice keeps the first revocation.
+    pub fn insert(&self, revoked_token: &RevokedToken, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::insert_into(revoked_tokens)
+            .values(revoked_token)
+            .on_conflict(jti)
+            .do_nothing()
+            .execute(connection)
+    }
+
+    /// Revocations of the tokens expiring after the given instant.
+    pub fn get_expiring_after(&self, after: DateTime<Utc>, connection: &mut PgConnection) -> QueryResult<Vec<RevokedToken>> {
+        revoked_tokens
+            .filter(expires_at.gt(after))
+            .select(RevokedToken::as_select())
+            .load(connection)
+    }
+
+    /// Deletes the revocations of the tokens expired before the given instant, returns how many.
+    pub fn prune(&self, before: DateTime<Utc>, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::delete(revoked_tokens.filter(expires_at.le(before)))
+            .execute(connection)
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use diesel::prelude::*;
+use uuid::Uuid;
+use crate::db::entities::author::slugify;
+use crate::db::entities::quote::{ApiQuote, Quote};
+use crate::db::entities::tag::{QuoteTag, Tag};
+use crate::db::schema::quote_tags;
+use crate::db::schema::tags::dsl::*;
+
+pub struct TagRepository;
+
+impl TagRepository {
+    /// Tags matching the normalized names, created on the fly when unknown.
+    pub fn find_or_create_all(&self, tag_names: &[String], connection: &mut PgConnection) -> QueryResult<Vec<Tag>> {
+        let mut slugs: Vec<String> = tag_names.iter().map(|tag_name| slugify(tag_name)).collect();
+        slugs.sort();
+        slugs.dedup();
+
+        let new_tags: Vec<Tag> = slugs
+            .iter()
+            .map(|slug| Tag { id: Uuid::new_v4().to_string(), name: slug.clone() })
+            .collect();
+
+        diesel::insert_into(tags)
+            .values(&new_tags)
+            .on_conflict(name)
+            .do_nothing()
+            .execute(connection)?;
+
+        tags
+            .filter(name.eq_any(slugs))
+            .select(Tag::as_select())
+            .order(name.asc())
+            .load(connection)
+    }
+
+    /// Replaces the tags of the quote, the write of the quote bumps its version.
+    pub fn set_quote_tags(&self, other_quote_id: String, tag_names: &[String], connection: &mut PgConnection) -> QueryResult<Vec<Tag>> {
+        let new_tags = self.find_or_create_all(tag_names, connection)?;
+
+        diesel::delete(quote_tags::table.filter(quote_tags::quote_id.eq(&other_quote_id)))
+            .execute(connection)?;
+
+        let links: Vec<QuoteTag> = new_tags
+            .iter()
+            .map(|tag| QuoteTag { quote_id: other_quote_id.clone(), tag_id: tag.id.clone() })
+            .collect();
+
+        diesel::insert_into(quote_tags::table)
+            .values(&links)
+            .execute(connection)?;
+
+        Ok(new_tags)
+    }
+
+    /// Tag names of each quote, keyed by quote id.
+    pub fn get_tag_names(&self, quote_ids: Vec<String>, connection: &mut PgConnection) -> QueryResult<HashMap<String, Vec<String>>> {
+        let rows: Vec<(String, String)> = quote_tags::table
+            .inner_join(tags)
+            .filter(quote_tags::quote_id.eq_any(quote_ids))
+            .select((quote_tags::quote_id, name))
+            .order(name.asc())
+            .load(connection)?;
+
+        let mut names: HashMap<String, Vec<String>> = HashMap::new();
+        for (quote_id, tag_name) in rows {
+            names.entry(quote_id).or_default().push(tag_name);
+        }
+
+        Ok(names)
+    }
+
+    /// Attaches their tags to the quotes, in a single query.
+    pub fn tag_quotes(&self, quotes: Vec<Quote>, connection: &mut PgConnection) -> QueryResult<Vec<ApiQuote>> {
+        let mut names = self.get_tag_names(
+            quotes.iter().map(|quote| quote.id.clone()).collect(),
+            connection
+        )?;
+
+        Ok(
+            quotes
+                .into_iter()
+                .map(|quote| {
+                    let quote_tags = names.remove(&quote.id).unwrap_or_default();
+                    ApiQuote { quote, tags: quote_tags }
+                })
+                .collect()
+        )
+    }
+
+    pub fn tag_quote(&self, quote: Quote, connection: &mut PgConnection) -> QueryResult<ApiQuote> {
+        let mut tagged = self.tag_quotes(vec![quote], connection)?;
+
+        Ok(tagged.remove(0))
+    }
+}
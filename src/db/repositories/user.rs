@@ -0,0 +1,27 @@
+use diesel::prelude::*;
+use crate::db::entities::user::User;
+use crate::db::schema::users::dsl::*;
+
+pub struct UserRepository;
+
+impl UserRepository {
+    pub fn insert(&self, user: &User, connection: &mut PgConnection) -> QueryResult<usize> {
+        diesel::insert_into(users)
+            .values(user)
+            .execute(connection)
+    }
+
+    pub fn get_user(&self, other_id: &str, connection: &mut PgConnection) -> QueryResult<User> {
+        users
+            .find(other_id)
+            .select(User::as_select())
+            .first(connection)
+    }
+
+    pub fn get_user_by_username(&self, other_username: &str, connection: &mut PgConnection) -> QueryResult<User> {
+        users
+            .filter(username.eq(other_username))
+            .select(User::as_select())
+            .first(connection)
+    }
+}
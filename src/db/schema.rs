@@ -1,9 +1,135 @@
 // @generated automatically by Diesel CLI.
 
+pub mod sql_types {
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tsvector", schema = "pg_catalog"))]
+    pub struct Tsvector;
+}
+
+diesel::table! {
+    api_keys (id) {
+        id -> Varchar,
+        name -> Varchar,
+        owner -> Varchar,
+        key_hash -> Varchar,
+        scope -> Varchar,
+        created_at -> Timestamptz,
+        expires_at -> Nullable<Timestamptz>,
+        last_used_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    authors (id) {
+        id -> Varchar,
+        name -> Varchar,
+        slug -> Varchar,
+        bio -> Nullable<Text>,
+        birth_date -> Nullable<Date>,
+        death_date -> Nullable<Date>,
+    }
+}
+
+diesel::table! {
+    daily_quotes (day) {
+        day -> Date,
+        quote_id -> Varchar,
+        pinned -> Bool,
+    }
+}
+
+diesel::table! {
+    quote_revisions (quote_id, revision) {
+        quote_id -> Varchar,
+        revision -> Int4,
+        author -> Varchar,
+        author_id -> Varchar,
+        quote -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    quote_tags (quote_id, tag_id) {
+        quote_id -> Varchar,
+        tag_id -> Varchar,
+    }
+}
+
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Tsvector;
+
     quotes (id) {
         id -> Text,
         author -> Text,
         quote -> Text,
+        search_vector -> Nullable<Tsvector>,
+        author_id -> Varchar,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+        version -> Int4,
+        created_by -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (id) {
+        id -> Varchar,
+        token_hash -> Varchar,
+        user_id -> Varchar,
+        family_id -> Varchar,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    revoked_tokens (jti) {
+        jti -> Varchar,
+        subject -> Nullable<Varchar>,
+        expires_at -> Timestamptz,
+        revoked_at -> Timestamptz,
     }
 }
+
+diesel::table! {
+    tags (id) {
+        id -> Varchar,
+        name -> Varchar,
+    }
+}
+
+diesel::table! {
+    users (id) {
+        id -> Varchar,
+        username -> Varchar,
+        password_hash -> Varchar,
+        scope -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::joinable!(daily_quotes -> quotes (quote_id));
+diesel::joinable!(quote_revisions -> quotes (quote_id));
+diesel::joinable!(quote_tags -> quotes (quote_id));
+diesel::joinable!(quote_tags -> tags (tag_id));
+diesel::joinable!(quotes -> authors (author_id));
+diesel::joinable!(refresh_tokens -> users (user_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    api_keys,
+    authors,
+    daily_quotes,
+    quote_revisions,
+    quote_tags,
+    quotes,
+    refresh_tokens,
+    revoked_tokens,
+    tags,
+    users,
+);
@@ -0,0 +1,74 @@
+// Diesel schema of `migrations_sqlite`, columns in the same order as the Postgres one.
+
+diesel::table! {
+    authors (id) {
+        id -> Text,
+        name -> Text,
+        slug -> Text,
+        bio -> Nullable<Text>,
+        birth_date -> Nullable<Date>,
+        death_date -> Nullable<Date>,
+    }
+}
+
+diesel::table! {
+    daily_quotes (day) {
+        day -> Date,
+        quote_id -> Text,
+        pinned -> Bool,
+    }
+}
+
+diesel::table! {
+    quote_revisions (quote_id, revision) {
+        quote_id -> Text,
+        revision -> Integer,
+        author -> Text,
+        author_id -> Text,
+        quote -> Text,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    quote_tags (quote_id, tag_id) {
+        quote_id -> Text,
+        tag_id -> Text,
+    }
+}
+
+diesel::table! {
+    quotes (id) {
+        id -> Text,
+        author -> Text,
+        quote -> Text,
+        author_id -> Text,
+        created_at -> TimestamptzSqlite,
+        updated_at -> TimestamptzSqlite,
+        deleted_at -> Nullable<TimestamptzSqlite>,
+        version -> Integer,
+        created_by -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Text,
+        name -> Text,
+    }
+}
+
+diesel::joinable!(daily_quotes -> quotes (quote_id));
+diesel::joinable!(quote_revisions -> quotes (quote_id));
+diesel::joinable!(quote_tags -> quotes (quote_id));
+diesel::joinable!(quote_tags -> tags (tag_id));
+diesel::joinable!(quotes -> authors (author_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    authors,
+    daily_quotes,
+    quote_revisions,
+    quote_tags,
+    quotes,
+    tags,
+);
@@ -0,0 +1,439 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use uuid::Uuid;
+use crate::db::entities::author::{Author, slugify};
+use crate::db::entities::daily_quote::DailyQuote;
+use crate::db::entities::quote::{ApiPayloadQuote, ApiQuote, Quote, SortOrder};
+use crate::db::entities::quote_revision::QuoteRevision;
+use crate::db::entities::tag::TagMode;
+use crate::db::migrations::MigrationError;
+use crate::db::repositories::quote::{QuoteCursor, QuoteFilters, QuoteSort};
+use crate::db::store::QuoteStore;
+use crate::http::error::MyError;
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    /// Quotes by id, iterated in id order like the database does
+    quotes: BTreeMap<String, Quote>,
+    /// Normalized tag names of each quote, sorted
+    tags: HashMap<String, Vec<String>>,
+    /// Authors by slug
+    authors: HashMap<String, Author>,
+    revisions: HashMap<String, Vec<QuoteRevision>>,
+    daily_quotes: HashMap<NaiveDate, DailyQuote>,
+}
+
+impl MemoryState {
+    fn find_or_create_author(&mut self, author_name: &str) -> Author {
+        self.authors
+            .entry(slugify(author_name))
+            .or_insert_with_key(|author_slug| Author {
+                id: Uuid::new_v4().to_string(),
+                name: author_name.trim().to_string(),
+                slug: author_slug.clone(),
+                bio: None,
+                birth_date: None,
+                death_date: None,
+            })
+            .clone()
+    }
+
+    fn set_tags(&mut self, quote_id: &str, tag_names: &[String]) {
+        let mut names: Vec<String> = tag_names.iter().map(|tag_name| slugify(tag_name)).collect();
+        names.sort();
+        names.dedup();
+
+        self.tags.insert(quote_id.to_string(), names);
+    }
+
+    fn tag_quote(&self, quote: Quote) -> ApiQuote {
+        let tags = self.tags.get(&quote.id).cloned().unwrap_or_default();
+
+        ApiQuote { quote, tags }
+    }
+
+    fn matches(&self, quote: &Quote, filters: &QuoteFilters) -> bool {
+        let quote_tags = self.tags.get(&quote.id).map(Vec::as_slice).unwrap_or_default();
+        let wanted: Vec<String> = filters.tags.iter().map(|tag| slugify(tag)).collect();
+
+        let tagged = wanted.is_empty() || match filters.tag_mode {
+            TagMode::Any => wanted.iter().any(|tag| quote_tags.contains(tag)),
+            TagMode::All => wanted.iter().all(|tag| quote_tags.contains(tag)),
+        };
+
+        tagged
+            && filters.author.as_ref().is_none_or(|author_name| slugify(&quote.author) == slugify(author_name))
+            && filters.updated_since.is_none_or(|since| quote.updated_at >= since)
+            && (filters.include_deleted || quote.deleted_at.is_none())
+    }
+
+    /// Quotes matching the filters, in id order.
+    fn filtered(&self, filters: &QuoteFilters) -> Vec<&Quote> {
+        self.quotes
+            .values()
+            .filter(|quote| self.matches(quote, filters))
+            .collect()
+    }
+
+    fn author(&self, id: &str) -> Result<&Author, MyError> {
+        self.authors
+            .values()
+            .find(|author| author.id == id)
+            .ok_or(MyError::NotFount)
+    }
+
+    fn live_quote(&self, id: &str) -> Result<&Quote, MyError> {
+        self.quotes
+            .get(id)
+            .filter(|quote| quote.deleted_at.is_none())
+            .ok_or(MyError::NotFount)
+    }
+
+    /// Records the content of the quote as a new revision numbered after its version, unless it did not change.
+    fn record_revision(&mut self, quote: &Quote) {
+        let history = self.revisions.entry(quote.id.clone()).or_default();
+        let new_revision = QuoteRevision {
+            quote_id: quote.id.clone(),
+            revision: quote.version,
+            author: quote.author.clone(),
+            author_id: quote.author_id.clone(),
+            quote: quote.quote.clone(),
+            created_at: Utc::now(),
+        };
+
+        if !history.last().is_some_and(|latest| latest.same_content(&new_revision)) {
+            history.push(new_revision);
+        }
+    }
+
+    fn write_quote(&mut self, id: &str, author_name: &str, content: String, tag_names: Option<&[String]>, expected_version: Option<i32>) -> Result<ApiQuote, MyError> {
+        let current = self.live_quote(id)?;
+        if expected_version.is_some_and(|expected| expected != current.version) {
+            return Err(MyError::PreconditionFailed);
+        }
+
+        let author = self.find_or_create_author(author_name);
+        let mut quote = self.live_quote(id)?.clone();
+        quote.author = author.name;
+        quote.author_id = author.id;
+        quote.quote = content;
+        quote.updated_at = Utc::now();
+        quote.version += 1;
+
+        if let Some(tag_names) = tag_names {
+            self.set_tags(id, tag_names);
+        }
+
+        self.record_revision(&quote);
+        self.quotes.insert(quote.id.clone(), quote.clone());
+
+        Ok(self.tag_quote(quote))
+    }
+}
+
+/// `QuoteStore` keeping everything in memory, for tests and demos without a database.
+#[derive(Debug, Default)]
+pub struct MemoryQuoteStore {
+    state: RwLock<MemoryState>,
+}
+
+impl MemoryQuoteStore {
+    fn read(&self) -> RwLockReadGuard<'_, MemoryState> {
+        // A panic while holding the lock leaves the state usable, every write is applied at once
+        self.state.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, MemoryState> {
+        self.state.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl QuoteStore for MemoryQuoteStore {
+    fn list_quotes(&self, limit: i64, cursor: Option<QuoteCursor>, sort: QuoteSort, filters: &QuoteFilters) -> Result<Vec<ApiQuote>, MyError> {
+        let state = self.read();
+        let key = |quote: &Quote| {
+            let position = QuoteCursor::after(quote, sort);
+            (position.timestamp, position.id)
+        };
+        let after = cursor.map(|cursor| (cursor.timestamp, cursor.id));
+
+        let mut items = state.filtered(filters);
+        items.sort_by_key(|quote| key(quote));
+        if sort.order == SortOrder::Desc {
+            items.reverse();
+        }
+
+        Ok(
+            items
+                .into_iter()
+                .filter(|quote| match (&after, sort.order) {
+                    (None, _) => true,
+                    (Some(after), SortOrder::Asc) => key(quote) > *after,
+                    (Some(after), SortOrder::Desc) => key(quote) < *after,
+                })
+                .take(limit.max(0) as usize)
+                .map(|quote| state.tag_quote(quote.clone()))
+                .collect()
+        )
+    }
+
+    fn search_quotes(&self, terms: &str, limit: i64) -> Result<Vec<ApiQuote>, MyError> {
+        let state = self.read();
+        let terms: Vec<String> = terms.split_whitespace().map(str::to_lowercase).collect();
+
+        // Every term must appear in the quote or its author, `-term` must not
+        Ok(
+            state
+                .filtered(&QuoteFilters::default())
+                .into_iter()
+                .filter(|quote| {
+                    let text = format!("{} {}", quote.quote, quote.author).to_lowercase();
+
+                    terms.iter().all(|term| match term.strip_prefix('-') {
+                        Some(excluded) => !text.contains(excluded),
+                        None => text.contains(term.as_str()),
+                    })
+                })
+                .take(limit.max(0) as usize)
+                .map(|quote| state.tag_quote(quote.clone()))
+                .collect()
+        )
+    }
+
+    fn random_quote(&self, filters: &QuoteFilters) -> Result<ApiQuote, MyError> {
+        let state = self.read();
+        let candidates = state.filtered(filters);
+
+        if candidates.is_empty() {
+            return Err(MyError::NotFount);
+        }
+
+        let picked = candidates[rand::thread_rng().gen_range(0..candidates.len())].clone();
+
+        Ok(state.tag_quote(picked))
+    }
+
+    fn daily_quote(&self, day: NaiveDate) -> Result<ApiQuote, MyError> {
+        let mut state = self.write();
+
+        if let Some(daily_quote) = state.daily_quotes.get(&day) {
+            if let Ok(quote) = state.live_quote(&daily_quote.quote_id) {
+                return Ok(state.tag_quote(quote.clone()));
+            }
+        }
+
+        // Nothing picked yet or the quote was deleted since, seeded by the date like the database
+        let candidates = state.filtered(&QuoteFilters::default());
+        if candidates.is_empty() {
+            return Err(MyError::NotFount);
+        }
+
+        let mut rng = StdRng::seed_from_u64(day.num_days_from_ce() as u64);
+        let picked = candidates[rng.gen_range(0..candidates.len())].clone();
+
+        state.daily_quotes.insert(day, DailyQuote { day, quote_id: picked.id.clone(), pinned: false });
+
+        Ok(state.tag_quote(picked))
+    }
+
+    fn pin_daily_quote(&self, daily_quote: DailyQuote) -> Result<DailyQuote, MyError> {
+        let mut state = self.write();
+
+        state.live_quote(&daily_quote.quote_id)?;
+        state.daily_quotes.insert(daily_quote.day, daily_quote.clone());
+
+        Ok(daily_quote)
+    }
+
+    fn get_quote(&self, id: &str, include_deleted: bool) -> Result<ApiQuote, MyError> {
+        let state = self.read();
+
+        let quote = match include_deleted {
+            true => state.quotes.get(id).ok_or(MyError::NotFount)?,
+            false => state.live_quote(id)?,
+        };
+
+        Ok(state.tag_quote(quote.clone()))
+    }
+
+    fn create_quote(&self, payload: &ApiPayloadQuote, created_by: Option<&str>) -> Result<ApiQuote, MyError> {
+        let mut state = self.write();
+
+        let author = state.find_or_create_author(&payload.author);
+        let quote = Quote {
+            id: Uuid::new_v4().to_string(),
+            author: author.name,
+            quote: payload.quote.to_string(),
+            author_id: author.id,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            version: 1,
+            created_by: created_by.map(str::to_string),
+        };
+
+        if let Some(tag_names) = &payload.tags {
+            state.set_tags(&quote.id, tag_names);
+        }
+
+        state.record_revision(&quote);
+        state.quotes.insert(quote.id.clone(), quote.clone());
+
+        Ok(state.tag_quote(quote))
+    }
+
+    fn update_quote(&self, id: &str, payload: &ApiPayloadQuote, expected_version: Option<i32>) -> Result<ApiQuote, MyError> {
+        self.write().write_quote(id, &payload.author, payload.quote.to_string(), payload.tags.as_deref(), expected_version)
+    }
+
+    fn delete_quote(&self, id: &str, expected_version: Option<i32>) -> Result<(), MyError> {
+        let mut state = self.write();
+
+        let mut quote = state.live_quote(id)?.clone();
+        if expected_version.is_some_and(|expected| expected != quote.version) {
+            return Err(MyError::PreconditionFailed);
+        }
+
+        quote.deleted_at = Some(Utc::now());
+        quote.version += 1;
+        state.quotes.insert(quote.id.clone(), quote);
+
+        Ok(())
+    }
+
+    fn restore_quote(&self, id: &str) -> Result<ApiQuote, MyError> {
+        let mut state = self.write();
+
+        let quote = state.quotes
+            .get_mut(id)
+            .filter(|quote| quote.deleted_at.is_some())
+            .ok_or(MyError::NotFount)?;
+        quote.deleted_at = None;
+        quote.version += 1;
+        let quote = quote.clone();
+
+        Ok(state.tag_quote(quote))
+    }
+
+    fn purge_deleted_quotes(&self, deleted_before: DateTime<Utc>) -> Result<usize, MyError> {
+        let mut state = self.write();
+
+        let purged: Vec<String> = state.quotes
+            .values()
+            .filter(|quote| quote.deleted_at.is_some_and(|deleted_at| deleted_at < deleted_before))
+            .map(|quote| quote.id.clone())
+            .collect();
+
+        for id in &purged {
+            state.quotes.remove(id);
+            state.tags.remove(id);
+            state.revisions.remove(id);
+            state.daily_quotes.retain(|_, daily_quote| &daily_quote.quote_id != id);
+        }
+
+        Ok(purged.len())
+    }
+
+    fn quote_revisions(&self, id: &str) -> Result<Vec<QuoteRevision>, MyError> {
+        let state = self.read();
+
+        state.live_quote(id)?;
+
+        Ok(state.revisions.get(id).cloned().unwrap_or_default())
+    }
+
+    fn quote_revision(&self, id: &str, revision: i32) -> Result<QuoteRevision, MyError> {
+        self.quote_revisions(id)?
+            .into_iter()
+            .find(|found| found.revision == revision)
+            .ok_or(MyError::NotFount)
+    }
+
+    fn revert_quote(&self, id: &str, revision: i32) -> Result<ApiQuote, MyError> {
+        let target = self.quote_revision(id, revision)?;
+
+        self.write().write_quote(id, &target.author, target.quote, None, None)
+    }
+
+    fn list_authors(&self, limit: i64, cursor: Option<String>) -> Result<Vec<Author>, MyError> {
+        let state = self.read();
+
+        let mut items: Vec<&Author> = state.authors
+            .values()
+            .filter(|author| cursor.as_ref().is_none_or(|after_slug| author.slug > *after_slug))
+            .collect();
+        items.sort_by(|a, b| a.slug.cmp(&b.slug));
+
+        Ok(items.into_iter().take(limit.max(0) as usize).cloned().collect())
+    }
+
+    fn get_author(&self, id: &str) -> Result<Author, MyError> {
+        Ok(self.read().author(id)?.clone())
+    }
+
+    fn create_author(&self, author: Author) -> Result<Author, MyError> {
+        let mut state = self.write();
+
+        // Same constraint names as the Postgres schema
+        if state.authors.contains_key(&author.slug) {
+            return Err(MyError::UniqueViolation("authors_slug_key".to_string()));
+        }
+        state.authors.insert(author.slug.clone(), author.clone());
+
+        Ok(author)
+    }
+
+    fn update_author(&self, author: Author) -> Result<Author, MyError> {
+        let mut state = self.write();
+
+        let current_slug = state.author(&author.id)?.slug.clone();
+        if current_slug != author.slug && state.authors.contains_key(&author.slug) {
+            return Err(MyError::UniqueViolation("authors_slug_key".to_string()));
+        }
+        state.authors.remove(&current_slug);
+        state.authors.insert(author.slug.clone(), author.clone());
+
+        let renamed: Vec<Quote> = state.quotes
+            .values()
+            .filter(|quote| quote.author_id == author.id && quote.author != author.name)
+            .map(|quote| Quote { author: author.name.clone(), updated_at: Utc::now(), version: quote.version + 1, ..quote.clone() })
+            .collect();
+        for quote in renamed {
+            state.record_revision(&quote);
+            state.quotes.insert(quote.id.clone(), quote);
+        }
+
+        Ok(author)
+    }
+
+    fn delete_author(&self, id: &str) -> Result<(), MyError> {
+        let mut state = self.write();
+
+        let author_slug = state.author(id)?.slug.clone();
+        if state.quotes.values().any(|quote| quote.author_id == id) {
+            return Err(MyError::ForeignKeyViolation("quotes_author_id_fkey".to_string()));
+        }
+        state.authors.remove(&author_slug);
+
+        Ok(())
+    }
+
+    /// Nothing to migrate, the state has no schema.
+    fn pending_migrations(&self) -> Result<Vec<String>, MigrationError> {
+        Ok(Vec::new())
+    }
+
+    fn run_pending_migrations(&self) -> Result<Vec<String>, MigrationError> {
+        Ok(Vec::new())
+    }
+}
+
+#[test]
+fn test_memory_quote_store() {
+    let store = MemoryQuoteStore::default();
+
+    crate::db::store::check_quote_store(&store);
+    crate::db::store::check_author_store(&store);
+}
@@ -0,0 +1,229 @@
+use std::sync::Arc;
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::config::env::Config;
+use crate::db::entities::author::Author;
+use crate::db::entities::daily_quote::DailyQuote;
+use crate::db::entities::quote::{ApiPayloadQuote, ApiQuote};
+use crate::db::entities::quote_revision::QuoteRevision;
+use crate::db::migrations::MigrationError;
+use crate::db::pool::{build_db_pool, DbPool};
+use crate::db::repositories::quote::{QuoteCursor, QuoteFilters, QuoteSort};
+use crate::http::error::MyError;
+
+pub mod memory;
+pub mod pg;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+/// Storage of the quotes along with what hangs off them: authors, tags, revisions and daily picks.
+///
+/// Methods block, controllers call them from `web::block`.
+pub trait QuoteStore: Send + Sync {
+    /// Quotes following the cursor in the sort order.
+    fn list_quotes(&self, limit: i64, cursor: Option<QuoteCursor>, sort: QuoteSort, filters: &QuoteFilters) -> Result<Vec<ApiQuote>, MyError>;
+
+    /// Quotes matching the search terms, most relevant first.
+    fn search_quotes(&self, terms: &str, limit: i64) -> Result<Vec<ApiQuote>, MyError>;
+
+    /// A quote drawn at random among the ones matching the filters, `NotFount` when none does.
+    fn random_quote(&self, filters: &QuoteFilters) -> Result<ApiQuote, MyError>;
+
+    /// Quote of the day, picked once then the same for every caller.
+    fn daily_quote(&self, day: NaiveDate) -> Result<ApiQuote, MyError>;
+
+    fn pin_daily_quote(&self, daily_quote: DailyQuote) -> Result<DailyQuote, MyError>;
+
+    fn get_quote(&self, id: &str, include_deleted: bool) -> Result<ApiQuote, MyError>;
+
+    /// Creates the quote on behalf of `created_by`, the subject of the caller credentials.
+    fn create_quote(&self, payload: &ApiPayloadQuote, created_by: Option<&str>) -> Result<ApiQuote, MyError>;
+
+    /// Writes the payload over the quote, with an expected version fails with
+    /// `PreconditionFailed` when the quote changed meanwhile.
+    fn update_quote(&self, id: &str, payload: &ApiPayloadQuote, expected_version: Option<i32>) -> Result<ApiQuote, MyError>;
+
+    /// Soft deletes the quote, same version check as `update_quote`.
+    fn delete_quote(&self, id: &str, expected_version: Option<i32>) -> Result<(), MyError>;
+
+    fn restore_quote(&self, id: &str) -> Result<ApiQuote, MyError>;
+
+    /// Hard deletes the quotes soft deleted before the given instant, returns how many.
+    fn purge_deleted_quotes(&self, deleted_before: DateTime<Utc>) -> Result<usize, MyError>;
+
+    /// Revisions of the quote, oldest first.
+    fn quote_revisions(&self, id: &str) -> Result<Vec<QuoteRevision>, MyError>;
+
+    fn quote_revision(&self, id: &str, revision: i32) -> Result<QuoteRevision, MyError>;
+
+    /// Sets the quote content back to the revision, recorded as a new revision.
+    fn revert_quote(&self, id: &str, revision: i32) -> Result<ApiQuote, MyError>;
+
+    /// Authors ordered by slug, following the slug of the cursor.
+    fn list_authors(&self, limit: i64, cursor: Option<String>) -> Result<Vec<Author>, MyError>;
+
+    fn get_author(&self, id: &str) -> Result<Author, MyError>;
+
+    /// Creates the author, `UniqueViolation` when another author has the same slug.
+    fn create_author(&self, author: Author) -> Result<Author, MyError>;
+
+    /// Writes the author over the stored one along with the author name kept on its quotes, each
+    /// recorded as a new revision, same `UniqueViolation` as `create_author`.
+    fn update_author(&self, author: Author) -> Result<Author, MyError>;
+
+    /// Deletes the author, `ForeignKeyViolation` while quotes still reference it.
+    fn delete_author(&self, id: &str) -> Result<(), MyError>;
+
+    /// Names of the schema migrations not applied yet, empty when the schema is up to date.
+    fn pending_migrations(&self) -> Result<Vec<String>, MigrationError>;
+
+    /// Applies the pending schema migrations, returns their versions.
+    fn run_pending_migrations(&self) -> Result<Vec<String>, MigrationError>;
+}
+
+/// Store shared by the workers, registered as `web::Data<dyn QuoteStore>`.
+pub type SharedQuoteStore = Arc<dyn QuoteStore>;
+
+/// Store selected by `QUOTES_STORE`, along with the Postgres pool of the features only Postgres
+/// backs: users, API keys and token revocations. The other stores go without them.
+pub fn from_config(config: &Config) -> (Option<DbPool>, SharedQuoteStore) {
+    match config.quotes_store.as_str() {
+        "postgres" => {
+            let pool = build_db_pool(config.database_url.to_string());
+            (Some(pool.clone()), Arc::new(pg::PgQuoteStore::new(pool)))
+        },
+        "memory" => (None, Arc::new(memory::MemoryQuoteStore::default())),
+        // DATABASE_URL is then the path of the SQLite file
+        #[cfg(feature = "sqlite")]
+        "sqlite" => (
+            None,
+            Arc::new(sqlite::SqliteQuoteStore::new(crate::db::pool::build_sqlite_pool(config.database_url.to_string())))
+        ),
+        other => panic!("Unknown QUOTES_STORE {}, expected postgres, memory or sqlite (with the sqlite feature)", other),
+    }
+}
+
+/// Scenario every store must go through the same way, run against each implementation.
+#[cfg(test)]
+pub fn check_quote_store(store: &dyn QuoteStore) {
+    use uuid::Uuid;
+    use crate::db::entities::quote::{QuoteSortField, SortOrder};
+
+    let theme = format!("theme-{}", Uuid::new_v4());
+    let payload = |quote: &str, tags: Option<Vec<String>>| ApiPayloadQuote {
+        author: "Professeur Tournesol".to_string(),
+        quote: quote.to_string(),
+        tags,
+    };
+
+    let first = store.create_quote(&payload("Les sous-marins requins sont parfaitement etanches", Some(vec![theme.clone(), "Science ".to_string()])), Some("tournesol")).unwrap();
+    let second = store.create_quote(&payload("Un peu plus a l'ouest", Some(vec![theme.clone()])), None).unwrap();
+    assert_eq!(first.tags, vec!["science".to_string(), theme.clone()]);
+    assert_eq!(store.get_quote(&first.quote.id, false).unwrap().quote.created_by.as_deref(), Some("tournesol"));
+    assert_eq!(store.get_quote(&first.quote.id, false).unwrap().quote.quote, first.quote.quote);
+
+    // Listing, filtered on tags and paginated
+    let filters = QuoteFilters { tags: vec![theme.clone()], ..QuoteFilters::default() };
+    let sort = QuoteSort { field: QuoteSortField::CreatedAt, order: SortOrder::Asc };
+    let page = store.list_quotes(1, None, sort, &filters).unwrap();
+    assert_eq!(page.len(), 1);
+    let next = store.list_quotes(10, Some(QuoteCursor::after(&page[0].quote, sort)), sort, &filters).unwrap();
+    assert_eq!(next.len(), 1);
+    assert_ne!(next[0].quote.id, page[0].quote.id);
+
+    let all_filters = QuoteFilters { tags: vec![theme.clone(), "science".to_string()], tag_mode: crate::db::entities::tag::TagMode::All, ..QuoteFilters::default() };
+    let all = store.list_quotes(10, None, QuoteSort::default(), &all_filters).unwrap();
+    assert_eq!(all.into_iter().map(|found| found.quote.id).collect::<Vec<String>>(), vec![first.quote.id.clone()]);
+
+    // Search, random and daily
+    let found = store.search_quotes("tournesol requins", 10).unwrap();
+    assert!(found.iter().any(|found_quote| found_quote.quote.id == first.quote.id));
+
+    let author_filters = QuoteFilters { author: Some("professeur tournesol ".to_string()), ..QuoteFilters::default() };
+    assert_eq!(store.random_quote(&author_filters).unwrap().quote.author_id, first.quote.author_id);
+    let nobody = QuoteFilters { author: Some(Uuid::new_v4().to_string()), ..QuoteFilters::default() };
+    assert!(matches!(store.random_quote(&nobody), Err(MyError::NotFount)));
+
+    let day = NaiveDate::from_ymd_opt(2100, 1, 1).unwrap();
+    store.pin_daily_quote(DailyQuote { day, quote_id: second.quote.id.clone(), pinned: true }).unwrap();
+    assert_eq!(store.daily_quote(day).unwrap().quote.id, second.quote.id);
+
+    // Updates, checked against the version
+    let changed = payload("Un peu plus a l'est", None);
+    assert!(matches!(store.update_quote(&second.quote.id, &changed, Some(second.quote.version - 1)), Err(MyError::PreconditionFailed)));
+    assert_eq!(second.quote.version, 1);
+    let updated = store.update_quote(&second.quote.id, &changed, Some(second.quote.version)).unwrap();
+    assert_eq!(updated.quote.version, second.quote.version + 1);
+    assert_eq!(updated.tags, vec![theme.clone()]);
+
+    // Revisions
+    let history = store.quote_revisions(&second.quote.id).unwrap();
+    assert_eq!(history.iter().map(|revision| revision.revision).collect::<Vec<i32>>(), vec![1, 2]);
+    assert_eq!(store.quote_revision(&second.quote.id, 1).unwrap().quote, second.quote.quote);
+    let reverted = store.revert_quote(&second.quote.id, 1).unwrap();
+    assert_eq!(reverted.quote.quote, second.quote.quote);
+    assert!(matches!(store.revert_quote(&second.quote.id, 42), Err(MyError::NotFount)));
+
+    // Soft delete, restore and purge
+    assert!(matches!(store.delete_quote(&first.quote.id, Some(0)), Err(MyError::PreconditionFailed)));
+    store.delete_quote(&first.quote.id, None).unwrap();
+    assert!(matches!(store.delete_quote(&first.quote.id, None), Err(MyError::NotFount)));
+    assert!(matches!(store.get_quote(&first.quote.id, false), Err(MyError::NotFount)));
+    assert!(store.get_quote(&first.quote.id, true).unwrap().quote.deleted_at.is_some());
+    assert!(store.restore_quote(&first.quote.id).unwrap().quote.deleted_at.is_none());
+
+    store.delete_quote(&first.quote.id, None).unwrap();
+    assert_eq!(store.purge_deleted_quotes(Utc::now() - chrono::Duration::days(1)).unwrap(), 0);
+    assert!(store.purge_deleted_quotes(Utc::now() + chrono::Duration::seconds(1)).unwrap() >= 1);
+    assert!(matches!(store.get_quote(&first.quote.id, true), Err(MyError::NotFount)));
+}
+
+/// Scenario of the authors every store must go through the same way.
+#[cfg(test)]
+pub fn check_author_store(store: &dyn QuoteStore) {
+    use uuid::Uuid;
+    use crate::db::entities::author::slugify;
+
+    let author = |name: &str| Author {
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        slug: slugify(name),
+        bio: None,
+        birth_date: None,
+        death_date: None,
+    };
+    let name = format!("Seraphin Lampion {}", Uuid::new_v4());
+
+    let created = store.create_author(author(&name)).unwrap();
+    assert_eq!(store.get_author(&created.id).unwrap().slug, slugify(&name));
+    assert!(matches!(store.create_author(author(&name.to_uppercase())), Err(MyError::UniqueViolation(_))));
+    assert!(matches!(store.get_author(&Uuid::new_v4().to_string()), Err(MyError::NotFount)));
+
+    // Paginated by slug
+    let other = store.create_author(author(&format!("{} bis", name))).unwrap();
+    let before = created.slug[..created.slug.len() - 1].to_string();
+    let page = store.list_authors(1, Some(before)).unwrap();
+    assert_eq!(page.iter().map(|found| found.id.clone()).collect::<Vec<String>>(), vec![created.id.clone()]);
+    let next = store.list_authors(1, Some(created.slug.clone())).unwrap();
+    assert_eq!(next.iter().map(|found| found.id.clone()).collect::<Vec<String>>(), vec![other.id.clone()]);
+
+    // Renaming the author renames its quotes, which keep it from being deleted
+    let quote = store.create_quote(&ApiPayloadQuote { author: name.clone(), quote: "Mille milliards de mille sabords".to_string(), tags: None }, None).unwrap();
+    assert_eq!(quote.quote.author_id, created.id);
+
+    let renamed = Author { name: format!("{} le Grand", name), slug: slugify(&format!("{} le Grand", name)), bio: Some("Assurances Mondass".to_string()), ..created.clone() };
+    assert_eq!(store.update_author(renamed.clone()).unwrap().bio, renamed.bio);
+    let requoted = store.get_quote(&quote.quote.id, false).unwrap();
+    assert_eq!(requoted.quote.author, renamed.name);
+    assert_eq!(requoted.quote.version, quote.quote.version + 1);
+    let history = store.quote_revisions(&quote.quote.id).unwrap();
+    assert_eq!(history.iter().map(|revision| (revision.revision, revision.author.clone())).collect::<Vec<(i32, String)>>(), vec![(1, name.clone()), (2, renamed.name.clone())]);
+    assert!(matches!(store.update_author(Author { name: other.name.clone(), slug: other.slug.clone(), ..renamed.clone() }), Err(MyError::UniqueViolation(_))));
+    assert!(matches!(store.update_author(author(&format!("Nobody {}", Uuid::new_v4()))), Err(MyError::NotFount)));
+
+    assert!(matches!(store.delete_author(&created.id), Err(MyError::ForeignKeyViolation(_))));
+    store.delete_quote(&quote.quote.id, None).unwrap();
+    store.purge_deleted_quotes(Utc::now() + chrono::Duration::seconds(1)).unwrap();
+    store.delete_author(&created.id).unwrap();
+    store.delete_author(&other.id).unwrap();
+    assert!(matches!(store.delete_author(&created.id), Err(MyError::NotFount)));
+}
@@ -0,0 +1,310 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::{Connection, PgConnection};
+use rand::Rng;
+use uuid::Uuid;
+use crate::db::entities::author::Author;
+use crate::db::entities::daily_quote::DailyQuote;
+use crate::db::entities::quote::{ApiPayloadQuote, ApiQuote, Quote};
+use crate::db::entities::quote_revision::QuoteRevision;
+use crate::db::migrations::{self, MigrationError, MIGRATIONS};
+use crate::db::pool::DbPool;
+use crate::db::repositories::author::AuthorRepository;
+use crate::db::repositories::daily_quote::DailyQuoteRepository;
+use crate::db::repositories::quote::{QuoteCursor, QuoteFilters, QuoteRepository, QuoteSort};
+use crate::db::repositories::quote_revision::QuoteRevisionRepository;
+use crate::db::repositories::tag::TagRepository;
+use crate::db::store::QuoteStore;
+use crate::http::error::MyError;
+
+/// `QuoteStore` backed by Postgres through the Diesel repositories.
+pub struct PgQuoteStore {
+    pool: DbPool,
+}
+
+impl PgQuoteStore {
+    pub fn new(pool: DbPool) -> PgQuoteStore {
+        PgQuoteStore { pool }
+    }
+
+    /// Writes the content over the quote and replaces its tags when given.
+    fn write_quote(&self, mut db_quote: Quote, author_name: &str, content: String, tag_names: Option<&[String]>, expected_version: Option<i32>, conn: &mut PgConnection) -> Result<ApiQuote, MyError> {
+        let update_promise = conn.transaction(|conn| {
+            let author = AuthorRepository.find_or_create(author_name, conn)?;
+
+            db_quote.quote = content;
+            db_quote.author = author.name;
+            db_quote.author_id = author.id;
+
+            // Locks the quote and bumps its version once, tags included
+            let updated_quote = QuoteRepository.update(db_quote, expected_version, conn)?;
+
+            if let Some(tag_names) = tag_names {
+                TagRepository.set_quote_tags(updated_quote.id.clone(), tag_names, conn)?;
+            }
+
+            TagRepository.tag_quote(updated_quote, conn)
+        });
+
+        match update_promise {
+            Ok(quote) => Ok(quote),
+            Err(diesel::result::Error::NotFound) if expected_version.is_some() => Err(MyError::PreconditionFailed),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl QuoteStore for PgQuoteStore {
+    fn list_quotes(&self, limit: i64, cursor: Option<QuoteCursor>, sort: QuoteSort, filters: &QuoteFilters) -> Result<Vec<ApiQuote>, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let items = QuoteRepository.get_quotes(Some(limit), cursor, sort, filters, &mut conn)?;
+
+        Ok(TagRepository.tag_quotes(items, &mut conn)?)
+    }
+
+    fn search_quotes(&self, terms: &str, limit: i64) -> Result<Vec<ApiQuote>, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let items = QuoteRepository.search(terms.to_string(), Some(limit), &mut conn)?;
+
+        Ok(TagRepository.tag_quotes(items, &mut conn)?)
+    }
+
+    fn random_quote(&self, filters: &QuoteFilters) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let count = QuoteRepository.count_quotes(filters, &mut conn)?;
+        if count == 0 {
+            return Err(MyError::NotFount);
+        }
+
+        let offset = rand::thread_rng().gen_range(0..count);
+        let quote = QuoteRepository.get_quote_at(offset, filters, &mut conn)?;
+
+        Ok(TagRepository.tag_quote(quote, &mut conn)?)
+    }
+
+    fn daily_quote(&self, day: NaiveDate) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let quote = conn.transaction(|conn| DailyQuoteRepository.get_or_pick(day, conn))?;
+
+        Ok(TagRepository.tag_quote(quote, &mut conn)?)
+    }
+
+    fn pin_daily_quote(&self, daily_quote: DailyQuote) -> Result<DailyQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        QuoteRepository.get_quote(daily_quote.quote_id.clone(), &mut conn)?;
+        DailyQuoteRepository.pin(daily_quote.clone(), &mut conn)?;
+
+        Ok(daily_quote)
+    }
+
+    fn get_quote(&self, id: &str, include_deleted: bool) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let quote = if include_deleted {
+            QuoteRepository.get_quote_including_deleted(id.to_string(), &mut conn)?
+        } else {
+            QuoteRepository.get_quote(id.to_string(), &mut conn)?
+        };
+
+        Ok(TagRepository.tag_quote(quote, &mut conn)?)
+    }
+
+    fn create_quote(&self, payload: &ApiPayloadQuote, created_by: Option<&str>) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        conn.transaction(|conn| {
+            let author = AuthorRepository.find_or_create(&payload.author, conn)?;
+            let new_quote = Quote {
+                id: Uuid::new_v4().to_string(),
+                author: author.name,
+                quote: payload.quote.to_string(),
+                author_id: author.id,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                deleted_at: None,
+                version: 1,
+                created_by: created_by.map(str::to_string),
+            };
+
+            let new_quote = QuoteRepository.insert(new_quote, conn)?;
+
+            if let Some(tag_names) = &payload.tags {
+                TagRepository.set_quote_tags(new_quote.id.clone(), tag_names, conn)?;
+            }
+
+            Ok(TagRepository.tag_quote(new_quote, conn)?)
+        })
+    }
+
+    fn update_quote(&self, id: &str, payload: &ApiPayloadQuote, expected_version: Option<i32>) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let db_quote = QuoteRepository.get_quote(id.to_string(), &mut conn)?;
+
+        self.write_quote(db_quote, &payload.author, payload.quote.to_string(), payload.tags.as_deref(), expected_version, &mut conn)
+    }
+
+    fn delete_quote(&self, id: &str, expected_version: Option<i32>) -> Result<(), MyError> {
+        let mut conn = self.pool.get()?;
+
+        match QuoteRepository.remove(id.to_string(), expected_version, &mut conn)? {
+            0 if expected_version.is_some() && QuoteRepository.get_quote(id.to_string(), &mut conn).is_ok() => Err(MyError::PreconditionFailed),
+            0 => Err(MyError::NotFount),
+            _ => Ok(()),
+        }
+    }
+
+    fn restore_quote(&self, id: &str) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let quote = QuoteRepository.restore(id.to_string(), &mut conn)?;
+
+        Ok(TagRepository.tag_quote(quote, &mut conn)?)
+    }
+
+    fn purge_deleted_quotes(&self, deleted_before: DateTime<Utc>) -> Result<usize, MyError> {
+        let mut conn = self.pool.get()?;
+
+        Ok(QuoteRepository.purge_deleted(deleted_before, &mut conn)?)
+    }
+
+    fn quote_revisions(&self, id: &str) -> Result<Vec<QuoteRevision>, MyError> {
+        let mut conn = self.pool.get()?;
+
+        QuoteRepository.get_quote(id.to_string(), &mut conn)?;
+
+        Ok(QuoteRevisionRepository.get_revisions(id.to_string(), &mut conn)?)
+    }
+
+    fn quote_revision(&self, id: &str, revision: i32) -> Result<QuoteRevision, MyError> {
+        let mut conn = self.pool.get()?;
+
+        QuoteRepository.get_quote(id.to_string(), &mut conn)?;
+
+        Ok(QuoteRevisionRepository.get_revision(id.to_string(), revision, &mut conn)?)
+    }
+
+    fn revert_quote(&self, id: &str, revision: i32) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let db_quote = QuoteRepository.get_quote(id.to_string(), &mut conn)?;
+        let target = QuoteRevisionRepository.get_revision(id.to_string(), revision, &mut conn)?;
+
+        // The author of an old revision may have been removed since, it is created again
+        self.write_quote(db_quote, &target.author, target.quote, None, None, &mut conn)
+    }
+
+    fn list_authors(&self, limit: i64, cursor: Option<String>) -> Result<Vec<Author>, MyError> {
+        let mut conn = self.pool.get()?;
+
+        Ok(AuthorRepository.get_authors(Some(limit), cursor, &mut conn)?)
+    }
+
+    fn get_author(&self, id: &str) -> Result<Author, MyError> {
+        let mut conn = self.pool.get()?;
+
+        Ok(AuthorRepository.get_author(id.to_string(), &mut conn)?)
+    }
+
+    fn create_author(&self, author: Author) -> Result<Author, MyError> {
+        let mut conn = self.pool.get()?;
+
+        // An author with the same slug makes the insert fail with a unique violation
+        AuthorRepository.insert(author.clone(), &mut conn)?;
+
+        Ok(author)
+    }
+
+    fn update_author(&self, author: Author) -> Result<Author, MyError> {
+        let mut conn = self.pool.get()?;
+
+        // Another author with the same slug makes the update fail with a unique violation
+        match AuthorRepository.update(author.clone(), &mut conn)? {
+            0 => Err(MyError::NotFount),
+            _ => Ok(author),
+        }
+    }
+
+    fn delete_author(&self, id: &str) -> Result<(), MyError> {
+        let mut conn = self.pool.get()?;
+
+        // Quotes referencing the author make the removal fail with a foreign key violation
+        match AuthorRepository.remove(id.to_string(), &mut conn)? {
+            0 => Err(MyError::NotFount),
+            _ => Ok(()),
+        }
+    }
+
+    fn pending_migrations(&self) -> Result<Vec<String>, MigrationError> {
+        let mut conn = self.pool.get()?;
+
+        migrations::pending(&mut conn, MIGRATIONS)
+    }
+
+    fn run_pending_migrations(&self) -> Result<Vec<String>, MigrationError> {
+        let mut conn = self.pool.get()?;
+
+        migrations::run_pending(&mut conn, MIGRATIONS)
+    }
+}
+
+#[test]
+fn test_pg_quote_store() {
+    use dotenv::dotenv;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+
+    let store = PgQuoteStore::new(pool);
+
+    assert_eq!(store.pending_migrations().unwrap(), Vec::<String>::new());
+    crate::db::store::check_quote_store(&store);
+    crate::db::store::check_author_store(&store);
+}
+
+#[test]
+fn test_pg_concurrent_updates() {
+    use dotenv::dotenv;
+    use crate::db::entities::quote::ApiPayloadQuote;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+
+    let store = PgQuoteStore::new(pool);
+    let payload = |n: usize| ApiPayloadQuote {
+        author: "Concurrent Author".to_string(),
+        quote: format!("Concurrent quote number {}", n),
+        tags: Some(vec!["concurrent".to_string()]),
+    };
+
+    let created = store.create_quote(&payload(0), None).unwrap();
+    assert_eq!(created.quote.version, 1);
+
+    std::thread::scope(|scope| {
+        for n in 1..=8 {
+            let store = &store;
+            let quote_id = created.quote.id.clone();
+            let payload = payload(n);
+            scope.spawn(move || store.update_quote(&quote_id, &payload, None).unwrap());
+        }
+    });
+
+    let updated = store.get_quote(&created.quote.id, false).unwrap();
+    assert_eq!(updated.quote.version, 9);
+
+    let revisions: Vec<i32> = store.quote_revisions(&created.quote.id).unwrap()
+        .into_iter()
+        .map(|revision| revision.revision)
+        .collect();
+    assert_eq!(revisions, (1..=9).collect::<Vec<i32>>());
+
+    store.delete_quote(&created.quote.id, None).unwrap();
+}
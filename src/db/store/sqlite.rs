@@ -0,0 +1,699 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use diesel::dsl::not;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use diesel::sqlite::Sqlite;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use uuid::Uuid;
+use crate::db::entities::author::{Author, slugify};
+use crate::db::entities::daily_quote::DailyQuote;
+use crate::db::entities::quote::{ApiPayloadQuote, ApiQuote, Quote, QuoteSortField, SortOrder};
+use crate::db::entities::quote_revision::QuoteRevision;
+use crate::db::entities::tag::TagMode;
+use crate::db::migrations::{self, MigrationError, SQLITE_MIGRATIONS};
+use crate::db::pool::SqlitePool;
+use crate::db::repositories::quote::{QuoteCursor, QuoteFilters, QuoteSort};
+use crate::db::schema_sqlite::{authors, daily_quotes, quote_revisions, quote_tags, quotes, tags};
+use crate::db::store::QuoteStore;
+use crate::http::error::MyError;
+
+type QuoteCondition = Box<dyn BoxableExpression<quotes::table, Sqlite, SqlType = Bool>>;
+
+/// Same criteria as `QuoteFilters::condition`, against the SQLite schema.
+fn condition(filters: &QuoteFilters) -> QuoteCondition {
+    let mut condition: QuoteCondition = Box::new(true.into_sql::<Bool>());
+
+    if let Some(author_name) = &filters.author {
+        condition = Box::new(condition.and(quotes::author_id.eq_any(
+            authors::table
+                .filter(authors::slug.eq(slugify(author_name)))
+                .select(authors::id)
+        )));
+    }
+
+    if !filters.tags.is_empty() {
+        let tag_names: Vec<String> = filters.tags.iter().map(|tag| slugify(tag)).collect();
+        let tagged = quote_tags::table
+            .inner_join(tags::table)
+            .filter(tags::name.eq_any(tag_names.clone()))
+            .select(quote_tags::quote_id);
+
+        condition = match filters.tag_mode {
+            TagMode::Any => Box::new(condition.and(quotes::id.eq_any(tagged))),
+            TagMode::All => {
+                let mut distinct_names = tag_names;
+                distinct_names.sort();
+                distinct_names.dedup();
+
+                Box::new(condition.and(quotes::id.eq_any(
+                    tagged
+                        .group_by(quote_tags::quote_id)
+                        .having(diesel::dsl::count(quote_tags::tag_id).eq(distinct_names.len() as i64))
+                )))
+            },
+        };
+    }
+
+    if let Some(since) = filters.updated_since {
+        condition = Box::new(condition.and(quotes::updated_at.ge(since)));
+    }
+
+    if !filters.include_deleted {
+        condition = Box::new(condition.and(quotes::deleted_at.is_null()));
+    }
+
+    condition
+}
+
+/// Escapes the `LIKE` wildcards of a search term.
+fn like_pattern(term: &str) -> String {
+    let escaped = term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+
+    format!("%{}%", escaped)
+}
+
+fn get_quote(id: &str, include_deleted: bool, connection: &mut SqliteConnection) -> QueryResult<Quote> {
+    let mut query = quotes::table
+        .find(id)
+        .select(quotes::all_columns)
+        .into_boxed();
+
+    if !include_deleted {
+        query = query.filter(quotes::deleted_at.is_null());
+    }
+
+    query.first(connection)
+}
+
+fn tag_quotes(items: Vec<Quote>, connection: &mut SqliteConnection) -> QueryResult<Vec<ApiQuote>> {
+    let rows: Vec<(String, String)> = quote_tags::table
+        .inner_join(tags::table)
+        .filter(quote_tags::quote_id.eq_any(items.iter().map(|quote| quote.id.clone()).collect::<Vec<String>>()))
+        .select((quote_tags::quote_id, tags::name))
+        .order(tags::name.asc())
+        .load(connection)?;
+
+    let mut names: HashMap<String, Vec<String>> = HashMap::new();
+    for (quote_id, tag_name) in rows {
+        names.entry(quote_id).or_default().push(tag_name);
+    }
+
+    Ok(
+        items
+            .into_iter()
+            .map(|quote| {
+                let quote_tags = names.remove(&quote.id).unwrap_or_default();
+                ApiQuote { quote, tags: quote_tags }
+            })
+            .collect()
+    )
+}
+
+fn tag_quote(quote: Quote, connection: &mut SqliteConnection) -> QueryResult<ApiQuote> {
+    let mut tagged = tag_quotes(vec![quote], connection)?;
+
+    Ok(tagged.remove(0))
+}
+
+fn find_or_create_author(author_name: &str, connection: &mut SqliteConnection) -> QueryResult<Author> {
+    let author_slug = slugify(author_name);
+
+    diesel::insert_into(authors::table)
+        .values((
+            authors::id.eq(Uuid::new_v4().to_string()),
+            authors::name.eq(author_name.trim()),
+            authors::slug.eq(&author_slug),
+        ))
+        .on_conflict(authors::slug)
+        .do_nothing()
+        .execute(connection)?;
+
+    authors::table
+        .filter(authors::slug.eq(author_slug))
+        .select(authors::all_columns)
+        .first(connection)
+}
+
+fn get_author(id: &str, connection: &mut SqliteConnection) -> QueryResult<Author> {
+    authors::table
+        .find(id)
+        .select(authors::all_columns)
+        .first(connection)
+}
+
+/// Fails with the unique violation Postgres reports when another author has the slug.
+fn check_slug_free(author: &Author, connection: &mut SqliteConnection) -> Result<(), MyError> {
+    let taken: i64 = authors::table
+        .filter(authors::slug.eq(&author.slug))
+        .filter(authors::id.ne(&author.id))
+        .count()
+        .get_result(connection)?;
+
+    match taken {
+        0 => Ok(()),
+        _ => Err(MyError::UniqueViolation("authors_slug_key".to_string())),
+    }
+}
+
+/// Replaces the tags of the quote, creating the unknown ones.
+fn set_quote_tags(quote_id: &str, tag_names: &[String], connection: &mut SqliteConnection) -> QueryResult<()> {
+    let mut slugs: Vec<String> = tag_names.iter().map(|tag_name| slugify(tag_name)).collect();
+    slugs.sort();
+    slugs.dedup();
+
+    for slug in &slugs {
+        diesel::insert_into(tags::table)
+            .values((tags::id.eq(Uuid::new_v4().to_string()), tags::name.eq(slug)))
+            .on_conflict(tags::name)
+            .do_nothing()
+            .execute(connection)?;
+    }
+
+    let tag_ids: Vec<String> = tags::table
+        .filter(tags::name.eq_any(slugs))
+        .select(tags::id)
+        .load(connection)?;
+
+    diesel::delete(quote_tags::table.filter(quote_tags::quote_id.eq(quote_id)))
+        .execute(connection)?;
+
+    let links: Vec<_> = tag_ids
+        .into_iter()
+        .map(|tag_id| (quote_tags::quote_id.eq(quote_id), quote_tags::tag_id.eq(tag_id)))
+        .collect();
+
+    diesel::insert_into(quote_tags::table)
+        .values(links)
+        .execute(connection)?;
+
+    Ok(())
+}
+
+/// Records the current content of the quote as a new revision numbered after its version, unless it did not change.
+fn record_revision(current: &Quote, connection: &mut SqliteConnection) -> QueryResult<()> {
+    let latest: Option<QuoteRevision> = quote_revisions::table
+        .filter(quote_revisions::quote_id.eq(&current.id))
+        .select(quote_revisions::all_columns)
+        .order(quote_revisions::revision.desc())
+        .first(connection)
+        .optional()?;
+    let new_revision = QuoteRevision {
+        quote_id: current.id.clone(),
+        revision: current.version,
+        author: current.author.clone(),
+        author_id: current.author_id.clone(),
+        quote: current.quote.clone(),
+        created_at: Utc::now(),
+    };
+
+    if latest.is_some_and(|latest| latest.same_content(&new_revision)) {
+        return Ok(());
+    }
+
+    diesel::insert_into(quote_revisions::table)
+        .values((
+            quote_revisions::quote_id.eq(new_revision.quote_id),
+            quote_revisions::revision.eq(new_revision.revision),
+            quote_revisions::author.eq(new_revision.author),
+            quote_revisions::author_id.eq(new_revision.author_id),
+            quote_revisions::quote.eq(new_revision.quote),
+            quote_revisions::created_at.eq(new_revision.created_at),
+        ))
+        .execute(connection)?;
+
+    Ok(())
+}
+
+/// Quote at the given position when ordered by id, used to draw quotes at random.
+fn draw_quote(filters: &QuoteFilters, rng: &mut impl Rng, connection: &mut SqliteConnection) -> Result<Quote, MyError> {
+    let count: i64 = quotes::table
+        .filter(condition(filters))
+        .count()
+        .get_result(connection)?;
+    if count == 0 {
+        return Err(MyError::NotFount);
+    }
+
+    Ok(
+        quotes::table
+            .select(quotes::all_columns)
+            .filter(condition(filters))
+            .order(quotes::id.asc())
+            .offset(rng.gen_range(0..count))
+            .first(connection)?
+    )
+}
+
+/// `QuoteStore` backed by a SQLite database file, for deployments without Postgres.
+///
+/// Timestamps are always bound from Rust, `CURRENT_TIMESTAMP` would not compare with them.
+pub struct SqliteQuoteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteQuoteStore {
+    pub fn new(pool: SqlitePool) -> SqliteQuoteStore {
+        SqliteQuoteStore { pool }
+    }
+
+    /// Writes the content over the quote and replaces its tags when given.
+    fn write_quote(&self, id: &str, author_name: &str, content: String, tag_names: Option<&[String]>, expected_version: Option<i32>) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        // Immediate so that the version checked is still the current one when writing
+        conn.immediate_transaction(|conn| {
+            let current = get_quote(id, false, conn)?;
+            if expected_version.is_some_and(|expected| expected != current.version) {
+                return Err(MyError::PreconditionFailed);
+            }
+
+            let author = find_or_create_author(author_name, conn)?;
+
+            diesel::update(quotes::table.find(id))
+                .set((
+                    quotes::author.eq(author.name),
+                    quotes::quote.eq(content),
+                    quotes::author_id.eq(author.id),
+                    quotes::updated_at.eq(Utc::now()),
+                    quotes::version.eq(quotes::version + 1),
+                ))
+                .execute(conn)?;
+
+            if let Some(tag_names) = tag_names {
+                set_quote_tags(id, tag_names, conn)?;
+            }
+
+            let updated = get_quote(id, false, conn)?;
+            record_revision(&updated, conn)?;
+
+            Ok(tag_quote(updated, conn)?)
+        })
+    }
+}
+
+impl QuoteStore for SqliteQuoteStore {
+    fn list_quotes(&self, limit: i64, cursor: Option<QuoteCursor>, sort: QuoteSort, filters: &QuoteFilters) -> Result<Vec<ApiQuote>, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let mut query = quotes::table
+            .select(quotes::all_columns)
+            .filter(condition(filters))
+            .limit(limit)
+            .into_boxed();
+
+        query = match (sort.field, sort.order) {
+            (QuoteSortField::Id, SortOrder::Asc) => query.order(quotes::id.asc()),
+            (QuoteSortField::Id, SortOrder::Desc) => query.order(quotes::id.desc()),
+            (QuoteSortField::CreatedAt, SortOrder::Asc) => query.order((quotes::created_at.asc(), quotes::id.asc())),
+            (QuoteSortField::CreatedAt, SortOrder::Desc) => query.order((quotes::created_at.desc(), quotes::id.desc())),
+            (QuoteSortField::UpdatedAt, SortOrder::Asc) => query.order((quotes::updated_at.asc(), quotes::id.asc())),
+            (QuoteSortField::UpdatedAt, SortOrder::Desc) => query.order((quotes::updated_at.desc(), quotes::id.desc())),
+        };
+
+        if let Some(cursor) = cursor {
+            let timestamp = cursor.timestamp.unwrap_or_default();
+
+            query = match (sort.field, sort.order) {
+                (QuoteSortField::Id, SortOrder::Asc) => query.filter(quotes::id.gt(cursor.id)),
+                (QuoteSortField::Id, SortOrder::Desc) => query.filter(quotes::id.lt(cursor.id)),
+                (QuoteSortField::CreatedAt, SortOrder::Asc) => query.filter(
+                    quotes::created_at.gt(timestamp).or(quotes::created_at.eq(timestamp).and(quotes::id.gt(cursor.id)))
+                ),
+                (QuoteSortField::CreatedAt, SortOrder::Desc) => query.filter(
+                    quotes::created_at.lt(timestamp).or(quotes::created_at.eq(timestamp).and(quotes::id.lt(cursor.id)))
+                ),
+                (QuoteSortField::UpdatedAt, SortOrder::Asc) => query.filter(
+                    quotes::updated_at.gt(timestamp).or(quotes::updated_at.eq(timestamp).and(quotes::id.gt(cursor.id)))
+                ),
+                (QuoteSortField::UpdatedAt, SortOrder::Desc) => query.filter(
+                    quotes::updated_at.lt(timestamp).or(quotes::updated_at.eq(timestamp).and(quotes::id.lt(cursor.id)))
+                ),
+            };
+        }
+
+        let items = query.load(&mut conn)?;
+
+        Ok(tag_quotes(items, &mut conn)?)
+    }
+
+    fn search_quotes(&self, terms: &str, limit: i64) -> Result<Vec<ApiQuote>, MyError> {
+        let mut conn = self.pool.get()?;
+
+        // No full text search, every term must appear in the quote or its author and `-term` must
+        // not, `LIKE` ignoring the case of ASCII letters
+        let mut search_condition = condition(&QuoteFilters::default());
+        for term in terms.split_whitespace() {
+            let (excluded, term) = match term.strip_prefix('-') {
+                Some(excluded_term) => (true, excluded_term),
+                None => (false, term),
+            };
+            if term.is_empty() {
+                continue;
+            }
+
+            let matching = quotes::quote.concat(" ").concat(quotes::author).like(like_pattern(term)).escape('\\');
+            search_condition = match excluded {
+                true => Box::new(search_condition.and(not(matching))),
+                false => Box::new(search_condition.and(matching)),
+            };
+        }
+
+        let items = quotes::table
+            .select(quotes::all_columns)
+            .filter(search_condition)
+            .order(quotes::id.asc())
+            .limit(limit)
+            .load(&mut conn)?;
+
+        Ok(tag_quotes(items, &mut conn)?)
+    }
+
+    fn random_quote(&self, filters: &QuoteFilters) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let quote = draw_quote(filters, &mut rand::thread_rng(), &mut conn)?;
+
+        Ok(tag_quote(quote, &mut conn)?)
+    }
+
+    fn daily_quote(&self, day: NaiveDate) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        conn.immediate_transaction(|conn| {
+            let daily_quote: Option<DailyQuote> = daily_quotes::table
+                .find(day)
+                .select(daily_quotes::all_columns)
+                .first(conn)
+                .optional()?;
+
+            if let Some(daily_quote) = daily_quote {
+                match get_quote(&daily_quote.quote_id, false, conn) {
+                    Ok(quote) => return Ok(tag_quote(quote, conn)?),
+                    // The quote was deleted since, pick another one
+                    Err(diesel::result::Error::NotFound) => {
+                        diesel::delete(daily_quotes::table.find(day)).execute(conn)?;
+                    },
+                    Err(err) => return Err(err.into()),
+                }
+            }
+
+            // Seeded by the date like the other stores
+            let mut rng = StdRng::seed_from_u64(day.num_days_from_ce() as u64);
+            let picked = draw_quote(&QuoteFilters::default(), &mut rng, conn)?;
+
+            diesel::insert_into(daily_quotes::table)
+                .values((
+                    daily_quotes::day.eq(day),
+                    daily_quotes::quote_id.eq(&picked.id),
+                    daily_quotes::pinned.eq(false),
+                ))
+                .execute(conn)?;
+
+            Ok(tag_quote(picked, conn)?)
+        })
+    }
+
+    fn pin_daily_quote(&self, daily_quote: DailyQuote) -> Result<DailyQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        get_quote(&daily_quote.quote_id, false, &mut conn)?;
+
+        diesel::insert_into(daily_quotes::table)
+            .values((
+                daily_quotes::day.eq(daily_quote.day),
+                daily_quotes::quote_id.eq(&daily_quote.quote_id),
+                daily_quotes::pinned.eq(daily_quote.pinned),
+            ))
+            .on_conflict(daily_quotes::day)
+            .do_update()
+            .set((
+                daily_quotes::quote_id.eq(&daily_quote.quote_id),
+                daily_quotes::pinned.eq(daily_quote.pinned),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(daily_quote)
+    }
+
+    fn get_quote(&self, id: &str, include_deleted: bool) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let quote = get_quote(id, include_deleted, &mut conn)?;
+
+        Ok(tag_quote(quote, &mut conn)?)
+    }
+
+    fn create_quote(&self, payload: &ApiPayloadQuote, created_by: Option<&str>) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        conn.immediate_transaction(|conn| {
+            let author = find_or_create_author(&payload.author, conn)?;
+            let new_quote = Quote {
+                id: Uuid::new_v4().to_string(),
+                author: author.name,
+                quote: payload.quote.to_string(),
+                author_id: author.id,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                deleted_at: None,
+                version: 1,
+                created_by: created_by.map(str::to_string),
+            };
+
+            diesel::insert_into(quotes::table)
+                .values((
+                    quotes::id.eq(&new_quote.id),
+                    quotes::author.eq(&new_quote.author),
+                    quotes::quote.eq(&new_quote.quote),
+                    quotes::author_id.eq(&new_quote.author_id),
+                    quotes::created_at.eq(new_quote.created_at),
+                    quotes::updated_at.eq(new_quote.updated_at),
+                    quotes::version.eq(new_quote.version),
+                    quotes::created_by.eq(&new_quote.created_by),
+                ))
+                .execute(conn)?;
+
+            if let Some(tag_names) = &payload.tags {
+                set_quote_tags(&new_quote.id, tag_names, conn)?;
+            }
+
+            record_revision(&new_quote, conn)?;
+
+            Ok(tag_quote(new_quote, conn)?)
+        })
+    }
+
+    fn update_quote(&self, id: &str, payload: &ApiPayloadQuote, expected_version: Option<i32>) -> Result<ApiQuote, MyError> {
+        self.write_quote(id, &payload.author, payload.quote.to_string(), payload.tags.as_deref(), expected_version)
+    }
+
+    fn delete_quote(&self, id: &str, expected_version: Option<i32>) -> Result<(), MyError> {
+        let mut conn = self.pool.get()?;
+
+        conn.immediate_transaction(|conn| {
+            let current = get_quote(id, false, conn)?;
+            if expected_version.is_some_and(|expected| expected != current.version) {
+                return Err(MyError::PreconditionFailed);
+            }
+
+            diesel::update(quotes::table.find(id))
+                .set((
+                    quotes::deleted_at.eq(Some(Utc::now())),
+                    quotes::version.eq(quotes::version + 1),
+                ))
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    fn restore_quote(&self, id: &str) -> Result<ApiQuote, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let restored = diesel::update(quotes::table.find(id).filter(quotes::deleted_at.is_not_null()))
+            .set((
+                quotes::deleted_at.eq(None::<DateTime<Utc>>),
+                quotes::version.eq(quotes::version + 1),
+            ))
+            .execute(&mut conn)?;
+        if restored == 0 {
+            return Err(MyError::NotFount);
+        }
+
+        let quote = get_quote(id, false, &mut conn)?;
+
+        Ok(tag_quote(quote, &mut conn)?)
+    }
+
+    fn purge_deleted_quotes(&self, deleted_before: DateTime<Utc>) -> Result<usize, MyError> {
+        let mut conn = self.pool.get()?;
+
+        Ok(diesel::delete(quotes::table.filter(quotes::deleted_at.lt(deleted_before))).execute(&mut conn)?)
+    }
+
+    fn quote_revisions(&self, id: &str) -> Result<Vec<QuoteRevision>, MyError> {
+        let mut conn = self.pool.get()?;
+
+        get_quote(id, false, &mut conn)?;
+
+        Ok(
+            quote_revisions::table
+                .filter(quote_revisions::quote_id.eq(id))
+                .select(quote_revisions::all_columns)
+                .order(quote_revisions::revision.asc())
+                .load(&mut conn)?
+        )
+    }
+
+    fn quote_revision(&self, id: &str, revision: i32) -> Result<QuoteRevision, MyError> {
+        let mut conn = self.pool.get()?;
+
+        get_quote(id, false, &mut conn)?;
+
+        Ok(
+            quote_revisions::table
+                .find((id, revision))
+                .select(quote_revisions::all_columns)
+                .first(&mut conn)?
+        )
+    }
+
+    fn revert_quote(&self, id: &str, revision: i32) -> Result<ApiQuote, MyError> {
+        let target = self.quote_revision(id, revision)?;
+
+        self.write_quote(id, &target.author, target.quote, None, None)
+    }
+
+    fn list_authors(&self, limit: i64, cursor: Option<String>) -> Result<Vec<Author>, MyError> {
+        let mut conn = self.pool.get()?;
+
+        let mut query = authors::table
+            .select(authors::all_columns)
+            .order(authors::slug.asc())
+            .limit(limit)
+            .into_boxed();
+
+        if let Some(after_slug) = cursor {
+            query = query.filter(authors::slug.gt(after_slug));
+        }
+
+        Ok(query.load(&mut conn)?)
+    }
+
+    fn get_author(&self, id: &str) -> Result<Author, MyError> {
+        let mut conn = self.pool.get()?;
+
+        Ok(get_author(id, &mut conn)?)
+    }
+
+    fn create_author(&self, author: Author) -> Result<Author, MyError> {
+        let mut conn = self.pool.get()?;
+
+        conn.immediate_transaction(|conn| {
+            check_slug_free(&author, conn)?;
+
+            diesel::insert_into(authors::table)
+                .values((
+                    authors::id.eq(&author.id),
+                    authors::name.eq(&author.name),
+                    authors::slug.eq(&author.slug),
+                    authors::bio.eq(&author.bio),
+                    authors::birth_date.eq(author.birth_date),
+                    authors::death_date.eq(author.death_date),
+                ))
+                .execute(conn)?;
+
+            Ok(author)
+        })
+    }
+
+    fn update_author(&self, author: Author) -> Result<Author, MyError> {
+        let mut conn = self.pool.get()?;
+
+        conn.immediate_transaction(|conn| {
+            get_author(&author.id, conn)?;
+            check_slug_free(&author, conn)?;
+
+            diesel::update(authors::table.find(&author.id))
+                .set((
+                    authors::name.eq(&author.name),
+                    authors::slug.eq(&author.slug),
+                    authors::bio.eq(&author.bio),
+                    authors::birth_date.eq(author.birth_date),
+                    authors::death_date.eq(author.death_date),
+                ))
+                .execute(conn)?;
+
+            let renamed: Vec<String> = quotes::table
+                .filter(quotes::author_id.eq(&author.id))
+                .filter(quotes::author.ne(&author.name))
+                .select(quotes::id)
+                .load(conn)?;
+
+            diesel::update(quotes::table.filter(quotes::id.eq_any(&renamed)))
+                .set((
+                    quotes::author.eq(&author.name),
+                    quotes::updated_at.eq(Utc::now()),
+                    quotes::version.eq(quotes::version + 1),
+                ))
+                .execute(conn)?;
+
+            for quote_id in &renamed {
+                record_revision(&get_quote(quote_id, true, conn)?, conn)?;
+            }
+
+            Ok(author)
+        })
+    }
+
+    fn delete_author(&self, id: &str) -> Result<(), MyError> {
+        let mut conn = self.pool.get()?;
+
+        conn.immediate_transaction(|conn| {
+            get_author(id, conn)?;
+
+            let quoted: i64 = quotes::table
+                .filter(quotes::author_id.eq(id))
+                .count()
+                .get_result(conn)?;
+            if quoted > 0 {
+                return Err(MyError::ForeignKeyViolation("quotes_author_id_fkey".to_string()));
+            }
+
+            diesel::delete(authors::table.find(id)).execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    fn pending_migrations(&self) -> Result<Vec<String>, MigrationError> {
+        let mut conn = self.pool.get()?;
+
+        migrations::pending(&mut conn, SQLITE_MIGRATIONS)
+    }
+
+    fn run_pending_migrations(&self) -> Result<Vec<String>, MigrationError> {
+        let mut conn = self.pool.get()?;
+
+        migrations::run_pending(&mut conn, SQLITE_MIGRATIONS)
+    }
+}
+
+#[test]
+fn test_sqlite_quote_store() {
+    let path = std::env::temp_dir().join(format!("quotes-{}.db", Uuid::new_v4()));
+    let store = SqliteQuoteStore::new(crate::db::pool::build_sqlite_pool(path.to_string_lossy().to_string()));
+
+    assert_eq!(store.pending_migrations().unwrap(), vec!["2026-10-18-165000_quotes".to_string(), "2026-10-18-190000_quotes_created_by".to_string()]);
+    assert_eq!(store.run_pending_migrations().unwrap().len(), 2);
+    assert!(store.pending_migrations().unwrap().is_empty());
+
+    crate::db::store::check_quote_store(&store);
+    crate::db::store::check_author_store(&store);
+    drop(store);
+
+    for suffix in ["", "-wal", "-shm"] {
+        std::fs::remove_file(format!("{}{}", path.to_string_lossy(), suffix)).ok();
+    }
+}
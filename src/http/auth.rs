@@ -0,0 +1,505 @@
+use actix_web::{HttpMessage, HttpRequest};
+use chrono::Utc;
+use derive_more::Display;
+use std::sync::{Arc, RwLock};
+use jsonwebtoken::Validation;
+use jsonwebtoken::jwk::JwkSet;
+use prometheus::{IntCounterVec, Opts};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use subtle::ConstantTimeEq;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::config::env::Config;
+use crate::db::entities::api_key::hash_key;
+use crate::db::pool::DbPool;
+use crate::db::repositories::api_key::ApiKeyRepository;
+use crate::http::keys::{KeyError, KeySources, Keyring, VerificationKey};
+use crate::http::error::MyError;
+#[cfg(test)]
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+
+/// `aud` claim, a single audience or several.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    pub fn contains(&self, audience: &str) -> bool {
+        match self {
+            Audience::One(one) => one == audience,
+            Audience::Many(many) => many.iter().any(|one| one == audience),
+        }
+    }
+}
+
+/// Claims of the verified bearer token, stored in the request extensions by the validator.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Audience>,
+    /// Seconds since the epoch, like `nbf` and `iat`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    /// Token id, needed to revoke the token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// Space separated scopes
+    #[serde(default)]
+    pub scope: String,
+}
+
+/// Grants every scope.
+pub const SCOPE_ADMIN: &str = "admin";
+pub const SCOPE_QUOTES_READ: &str = "quotes:read";
+pub const SCOPE_QUOTES_WRITE: &str = "quotes:write";
+pub const SCOPE_QUOTES_DELETE: &str = "quotes:delete";
+
+impl Claims {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope.split_whitespace().any(|granted| granted == scope || granted == SCOPE_ADMIN)
+    }
+}
+
+/// Fails with a 403 problem unless the verified token grants the scope.
+pub fn require_scope(req: &HttpRequest, scope: &str) -> Result<(), MyError> {
+    let granted = req.extensions()
+        .get::<Claims>()
+        .map(|claims| claims.has_scope(scope))
+        .unwrap_or(false);
+
+    match granted {
+        true => Ok(()),
+        false => Err(MyError::MissingScope(scope.to_string())),
+    }
+}
+
+/// Test middleware standing in for the validator, it grants the scopes unless the request already has claims.
+#[cfg(test)]
+pub fn grant_scopes<S, B>(scopes: &'static str) -> impl Fn(ServiceRequest, &S) -> S::Future + Clone
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    move |req, service| {
+        if !req.extensions().contains::<Claims>() {
+            req.extensions_mut().insert(Claims { scope: scopes.to_string(), ..Claims::default() });
+        }
+
+        service.call(req)
+    }
+}
+
+/// Header carrying an API key, accepted instead of a bearer token.
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Prefix of the subject of the requests made with an API key, followed by the key id.
+pub const API_KEY_SUBJECT_PREFIX: &str = "apikey:";
+
+/// Claims of the requests made with the API key. Blocks on the database.
+///
+/// The subject names the key rather than its free text owner, which could be the subject of a user.
+pub fn authenticate_api_key(key: &str, pool: &DbPool) -> Result<Claims, MyError> {
+    let api_key_repository = ApiKeyRepository;
+    let now = Utc::now();
+    let mut conn = pool.get()?;
+
+    let api_key = match api_key_repository.get_active_by_hash(&hash_key(key), now, &mut conn) {
+        Err(diesel::result::Error::NotFound) => return Err(MyError::InvalidApiKey),
+        found => found?,
+    };
+    api_key_repository.touch(&api_key.id, now, &mut conn)?;
+
+    Ok(Claims {
+        sub: Some(format!("{}{}", API_KEY_SUBJECT_PREFIX, api_key.id)),
+        exp: api_key.expires_at.map(|expires_at| expires_at.timestamp()),
+        scope: api_key.scope,
+        ..Claims::default()
+    })
+}
+
+/// Why a bearer token is refused, sent back as the `WWW-Authenticate` error description.
+#[derive(Debug, Display, PartialEq)]
+pub enum TokenError {
+    #[display(fmt = "The token signature or format is invalid")]
+    Invalid,
+
+    #[display(fmt = "No key verifies the token kid and alg")]
+    UnknownKey,
+
+    #[display(fmt = "The token has no exp claim")]
+    MissingExpiry,
+
+    #[display(fmt = "The token expired")]
+    Expired,
+
+    #[display(fmt = "The token is not valid yet")]
+    NotYetValid,
+
+    #[display(fmt = "The token issuer is not accepted")]
+    WrongIssuer,
+
+    #[display(fmt = "The token audience is not accepted")]
+    WrongAudience,
+
+    #[display(fmt = "The token was revoked")]
+    Revoked,
+}
+
+/// Label of the tokens signed with `JWT_SECRET`, which have no `kid`.
+const SHARED_SECRET_KID: &str = "-";
+/// Label of the tokens no key matches, their `kid` is not used as label.
+pub const UNKNOWN_KID: &str = "unknown";
+
+/// Signs and verifies the bearer tokens, clones share the keyring.
+#[derive(Debug, Clone)]
+pub struct JwtSettings {
+    keyring: Arc<RwLock<Arc<Keyring>>>,
+    /// Read again by `reload`, none when the keyring was built in code
+    sources: Option<KeySources>,
+    /// Required `iss`, not checked when empty
+    pub issuer: String,
+    /// Required `aud`, not checked when empty
+    pub audience: String,
+    /// Clock skew tolerated on `exp` and `nbf`
+    pub leeway_seconds: i64,
+    /// Verified tokens by `kid` and outcome, to tell when a rotated key is no longer used
+    pub verifications: IntCounterVec,
+}
+
+fn verifications_counter(namespace: &str) -> IntCounterVec {
+    IntCounterVec::new(
+        Opts::new("jwt_verifications_total", "Bearer tokens verified, by key and outcome").namespace(namespace),
+        &["kid", "outcome"],
+    ).expect("metric names should be valid")
+}
+
+impl JwtSettings {
+    /// Neither issuer nor audience checked.
+    pub fn new(keyring: Keyring) -> Self {
+        JwtSettings {
+            keyring: Arc::new(RwLock::new(Arc::new(keyring))),
+            sources: None,
+            issuer: "".to_string(),
+            audience: "".to_string(),
+            leeway_seconds: 60,
+            verifications: verifications_counter(""),
+        }
+    }
+
+    /// HS256 with the shared secret.
+    #[cfg(test)]
+    pub fn from_secret(secret: &str) -> Self {
+        JwtSettings::new(Keyring::new(crate::http::keys::SigningKey::from_secret(None, secret)))
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self, KeyError> {
+        let sources = KeySources::from_config(config);
+
+        Ok(JwtSettings {
+            sources: Some(sources.clone()),
+            issuer: config.jwt_issuer.clone(),
+            audience: config.jwt_audience.clone(),
+            leeway_seconds: config.jwt_leeway as i64,
+            verifications: verifications_counter(&config.prometheus_namespace),
+            ..JwtSettings::new(Keyring::load(&sources)?)
+        })
+    }
+
+    pub fn keyring(&self) -> Arc<Keyring> {
+        self.keyring.read().expect("keyring lock poisoned").clone()
+    }
+
+    /// Loads the keys again, the current ones stay when loading fails. Tells whether the kids changed.
+    pub fn reload(&self) -> Result<bool, KeyError> {
+        let Some(sources) = &self.sources else {
+            return Ok(false);
+        };
+
+        let keyring = Arc::new(Keyring::load(sources)?);
+        let previous = std::mem::replace(&mut *self.keyring.write().expect("keyring lock poisoned"), keyring.clone());
+
+        Ok(previous.signing_key.kid != keyring.signing_key.kid || previous.kids() != keyring.kids())
+    }
+
+    /// Public keys of the keyring, none while tokens are signed with shared secrets.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet { keys: self.keyring().published_keys.clone() }
+    }
+
+    /// Signs a bearer token for the subject, valid `ttl_seconds` from now.
+    pub fn sign(&self, subject: &str, scopes: &[String], ttl_seconds: i64) -> String {
+        let issued_at = Utc::now().timestamp();
+
+        let claims = Claims {
+            sub: Some(subject.to_string()),
+            iss: Some(self.issuer.clone()).filter(|issuer| !issuer.is_empty()),
+            aud: Some(Audience::One(self.audience.clone())).filter(|_| !self.audience.is_empty()),
+            exp: Some(issued_at + ttl_seconds),
+            nbf: None,
+            iat: Some(issued_at),
+            jti: Some(Uuid::new_v4().to_string()),
+            scope: scopes.join(" "),
+        };
+
+        self.keyring().signing_key.sign(&claims)
+    }
+
+    /// Picks the key by the token header, checks the signature then the registered claims.
+    pub fn verify(&self, token: &str) -> Result<Claims, TokenError> {
+        let keyring = self.keyring();
+        let key = jsonwebtoken::decode_header(token)
+            .map_err(|_| TokenError::Invalid)
+            .and_then(|header| keyring.find(header.kid.as_deref(), header.alg).ok_or(TokenError::UnknownKey));
+
+        let kid = match &key {
+            Ok(key) => key.kid.as_deref().unwrap_or(SHARED_SECRET_KID),
+            Err(_) => UNKNOWN_KID,
+        };
+        let verified = key.and_then(|key| self.verify_with(key, token));
+
+        let outcome = if verified.is_ok() { "accepted" } else { "rejected" };
+        self.verifications.with_label_values(&[kid, outcome]).inc();
+
+        verified
+    }
+
+    fn verify_with(&self, key: &VerificationKey, token: &str) -> Result<Claims, TokenError> {
+        check_claims(decode_signed(key, token)?, &self.issuer, &self.audience, self.leeway_seconds)
+    }
+}
+
+/// Checks only the signature, `check_claims` then tells why a token is refused.
+pub fn decode_signed<T: DeserializeOwned>(key: &VerificationKey, token: &str) -> Result<T, TokenError> {
+    let mut validation = Validation::new(key.algorithm);
+    validation.required_spec_claims.clear();
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+
+    Ok(jsonwebtoken::decode(token, &key.key, &validation).map_err(|_| TokenError::Invalid)?.claims)
+}
+
+/// Checks the registered claims, `issuer` and `audience` are not checked when empty.
+pub fn check_claims(claims: Claims, issuer: &str, audience: &str, leeway_seconds: i64) -> Result<Claims, TokenError> {
+    let now = Utc::now().timestamp();
+
+    match claims.exp {
+        None => return Err(TokenError::MissingExpiry),
+        Some(expires_at) if now - leeway_seconds >= expires_at => return Err(TokenError::Expired),
+        _ => {},
+    }
+
+    if claims.nbf.is_some_and(|not_before| now + leeway_seconds < not_before) {
+        return Err(TokenError::NotYetValid);
+    }
+
+    if !issuer.is_empty() && claims.iss.as_deref() != Some(issuer) {
+        return Err(TokenError::WrongIssuer);
+    }
+
+    if !audience.is_empty() && !claims.aud.as_ref().is_some_and(|aud| aud.contains(audience)) {
+        return Err(TokenError::WrongAudience);
+    }
+
+    Ok(claims)
+}
+
+/// Client credentials exchanged at `POST /auth/token`.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ApiTokenRequest {
+    #[schema(example = "playground")]
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+/// Token to revoke at `POST /auth/revoke`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiRevokeRequest {
+    pub token: String,
+}
+
+/// Bearer token answered by `POST /auth/token`, shaped like an OAuth2 token response.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiToken {
+    pub access_token: String,
+    #[schema(example = "Bearer")]
+    pub token_type: String,
+    /// Validity in seconds
+    #[schema(example = 900)]
+    pub expires_in: i64,
+    /// Single use token to get the next access token, for users only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+/// Issues short-lived tokens to the configured client.
+#[derive(Debug, Clone)]
+pub struct TokenIssuer {
+    pub jwt: JwtSettings,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: Vec<String>,
+    pub ttl_seconds: i64,
+    pub refresh_ttl_seconds: i64,
+}
+
+impl TokenIssuer {
+    pub fn from_config(config: &Config, jwt: JwtSettings) -> Self {
+        TokenIssuer {
+            jwt,
+            client_id: config.auth_client_id.clone(),
+            client_secret: config.auth_client_secret.clone(),
+            scopes: config.auth_client_scopes.split_whitespace().map(str::to_string).collect(),
+            ttl_seconds: config.auth_token_ttl as i64,
+            refresh_ttl_seconds: config.auth_refresh_token_ttl as i64,
+        }
+    }
+
+    /// Compares in constant time, nothing authenticates while no secret is configured.
+    pub fn authenticates(&self, credentials: &ApiTokenRequest) -> bool {
+        let same_id: bool = credentials.client_id.as_bytes().ct_eq(self.client_id.as_bytes()).into();
+        let same_secret: bool = credentials.client_secret.as_bytes().ct_eq(self.client_secret.as_bytes()).into();
+
+        !self.client_secret.is_empty() && same_id && same_secret
+    }
+
+    pub fn issue(&self) -> ApiToken {
+        self.issue_to(&self.client_id, &self.scopes, None)
+    }
+
+    /// Access token of another subject, e.g. a user who logged in.
+    pub fn issue_to(&self, subject: &str, scopes: &[String], refresh_token: Option<String>) -> ApiToken {
+        ApiToken {
+            access_token: self.jwt.sign(subject, scopes, self.ttl_seconds),
+            token_type: "Bearer".to_string(),
+            expires_in: self.ttl_seconds,
+            refresh_token,
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_jwt_settings() -> JwtSettings {
+    JwtSettings {
+        issuer: "rust-playground".to_string(),
+        audience: "rust-playground".to_string(),
+        leeway_seconds: 30,
+        ..JwtSettings::from_secret("NOT_A_SECRET")
+    }
+}
+
+#[test]
+fn test_sign_and_verify() {
+    let jwt = test_jwt_settings();
+
+    let claims = jwt.verify(&jwt.sign("batch-import", &["admin".to_string(), "quotes:read".to_string()], 60)).unwrap();
+    assert_eq!(claims.sub.as_deref(), Some("batch-import"));
+    assert_eq!(claims.aud, Some(Audience::One("rust-playground".to_string())));
+    assert!(claims.has_scope("admin") && claims.has_scope("quotes:read"));
+    assert_eq!(claims.exp.unwrap() - claims.iat.unwrap(), 60);
+
+    let other_secret = JwtSettings::from_secret("OTHER");
+    assert_eq!(other_secret.verify(&jwt.sign("batch", &[], 60)), Err(TokenError::Invalid));
+    assert_eq!(jwt.verify("not.a.token"), Err(TokenError::Invalid));
+}
+
+#[test]
+fn test_verify_registered_claims() {
+    let jwt = test_jwt_settings();
+    let now = Utc::now().timestamp();
+    let sign = |claims: Claims| jwt.keyring().signing_key.sign(&claims);
+    let valid = || Claims {
+        iss: Some("rust-playground".to_string()),
+        aud: Some(Audience::Many(vec!["other".to_string(), "rust-playground".to_string()])),
+        exp: Some(now + 60),
+        ..Claims::default()
+    };
+
+    assert!(jwt.verify(&sign(valid())).is_ok());
+    assert_eq!(jwt.verify(&sign(Claims { exp: None, ..valid() })), Err(TokenError::MissingExpiry));
+    assert_eq!(jwt.verify(&sign(Claims { exp: Some(now - 60), ..valid() })), Err(TokenError::Expired));
+    assert_eq!(jwt.verify(&sign(Claims { nbf: Some(now + 60), ..valid() })), Err(TokenError::NotYetValid));
+    assert_eq!(jwt.verify(&sign(Claims { iss: Some("someone".to_string()), ..valid() })), Err(TokenError::WrongIssuer));
+    assert_eq!(jwt.verify(&sign(Claims { aud: None, ..valid() })), Err(TokenError::WrongAudience));
+
+    // Clock skew within the leeway is tolerated
+    assert!(jwt.verify(&sign(Claims { exp: Some(now - 10), nbf: Some(now + 10), ..valid() })).is_ok());
+
+    // Issuer and audience are not required when not configured
+    let lenient = JwtSettings { issuer: "".to_string(), audience: "".to_string(), ..test_jwt_settings() };
+    assert!(lenient.verify(&sign(Claims { exp: Some(now + 60), ..Claims::default() })).is_ok());
+}
+
+#[test]
+fn test_verify_by_kid() {
+    use crate::http::keys::{SigningKey, TEST_KEYS};
+
+    let signing_key = |kid: &str, name: &str| SigningKey::from_pem(kid, &std::fs::read(format!("{}/{}.pem", TEST_KEYS, name)).unwrap()).unwrap();
+    let mut keyring = Keyring::new(signing_key("ed-2026", "ed25519"));
+    keyring.verification_keys.push(
+        VerificationKey::from_pem("rsa-2026", &std::fs::read(format!("{}/rsa.pub.pem", TEST_KEYS)).unwrap()).unwrap()
+    );
+    let jwt = JwtSettings::new(keyring);
+
+    let claims = jwt.verify(&jwt.sign("batch-import", &["admin".to_string()], 60)).unwrap();
+    assert_eq!(claims.sub.as_deref(), Some("batch-import"));
+    assert_eq!(jwt.jwks().keys.len(), 1);
+
+    // Neither the shared secret nor another private key are known
+    assert_eq!(jwt.verify(&test_jwt_settings().sign("batch", &[], 60)), Err(TokenError::UnknownKey));
+    let other = JwtSettings::new(Keyring::new(signing_key("ed-2026", "ec")));
+    assert_eq!(jwt.verify(&other.sign("batch", &[], 60)), Err(TokenError::UnknownKey));
+
+    // Public keys of other issuers verify their tokens
+    let rsa_kid = JwtSettings::new(Keyring::new(signing_key("rsa-2026", "rsa")));
+    assert!(jwt.verify(&rsa_kid.sign("batch", &[], 60)).is_ok());
+
+    assert_eq!(jwt.verifications.with_label_values(&["ed-2026", "accepted"]).get(), 1);
+    assert_eq!(jwt.verifications.with_label_values(&["rsa-2026", "accepted"]).get(), 1);
+    assert_eq!(jwt.verifications.with_label_values(&["unknown", "rejected"]).get(), 2);
+}
+
+#[test]
+fn test_reload_keyring() {
+    use std::fs;
+    use uuid::Uuid;
+
+    let path = std::env::temp_dir().join(format!("keyring-{}.json", Uuid::new_v4()));
+    let sources = KeySources { keyring_file: path.to_string_lossy().to_string(), ..KeySources::default() };
+
+    fs::write(&path, r#"{"signing_kid": "2026-q3", "keys": [{"kid": "2026-q3", "secret": "Q3_SECRET"}]}"#).unwrap();
+    let jwt = JwtSettings { sources: Some(sources.clone()), ..JwtSettings::new(Keyring::load(&sources).unwrap()) };
+    let issuer = jwt.clone();
+    let before_rotation = jwt.sign("batch", &[], 60);
+
+    // Q4 signs, Q3 still verifies the tokens it signed
+    fs::write(&path, r#"{"signing_kid": "2026-q4", "keys": [{"kid": "2026-q4", "secret": "Q4_SECRET"}, {"kid": "2026-q3", "secret": "Q3_SECRET"}]}"#).unwrap();
+    assert!(jwt.reload().unwrap());
+    assert!(!jwt.reload().unwrap());
+
+    let after_rotation = issuer.sign("batch", &[], 60);
+    assert_eq!(jsonwebtoken::decode_header(&after_rotation).unwrap().kid.as_deref(), Some("2026-q4"));
+    assert!(jwt.verify(&before_rotation).is_ok() && jwt.verify(&after_rotation).is_ok());
+
+    // A broken keyring keeps the current keys
+    fs::write(&path, "{").unwrap();
+    assert!(jwt.reload().is_err());
+    assert!(jwt.verify(&after_rotation).is_ok());
+
+    // Q3 retired
+    fs::write(&path, r#"{"signing_kid": "2026-q4", "keys": [{"kid": "2026-q4", "secret": "Q4_SECRET"}]}"#).unwrap();
+    assert!(jwt.reload().unwrap());
+    assert_eq!(jwt.verify(&before_rotation), Err(TokenError::UnknownKey));
+
+    fs::remove_file(path).ok();
+}
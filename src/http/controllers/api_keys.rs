@@ -0,0 +1,180 @@
+use crate::http;
+use crate::http::auth::{require_scope, SCOPE_ADMIN};
+use crate::db::repositories::api_key::ApiKeyRepository;
+use crate::db::entities::api_key::{ApiKey, ApiPayloadApiKey, ApiCreatedApiKey, generate_key, hash_key};
+use crate::db::pool::DbPool;
+use chrono::Utc;
+use validator::Validate;
+use actix_web::web::{Path, Json, self};
+use actix_web::{HttpRequest, HttpResponse};
+use actix_web::{
+    get, delete, post,
+    Result
+};
+use uuid::Uuid;
+
+#[utoipa::path(
+    path = "/api/api-keys",
+    responses(
+        (status = 200, description = "Every API key, revoked ones included, newest first", body = [ApiKey]),
+        (status = 403, description = "Token lacks the admin scope", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["admin"]),
+        ("api_key" = ["admin"])
+    )
+)]
+#[get("/api-keys")]
+pub async fn list(req: HttpRequest, pool: web::Data<DbPool>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_ADMIN)?;
+
+    let api_key_repository = ApiKeyRepository;
+
+    let api_keys = web::block(move || {
+        let mut conn = pool.get()?;
+
+        Ok::<_, http::error::MyError>(api_key_repository.get_api_keys(&mut conn)?)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(api_keys))
+}
+
+#[utoipa::path(
+    path = "/api/api-keys",
+    request_body = ApiPayloadApiKey,
+    responses(
+        (status = 201, description = "API key created, the key is not shown again", body = ApiCreatedApiKey),
+        (status = 403, description = "Token lacks the admin scope", body = ApiProblem),
+        (status = 422, description = "Validation error", body = ApiProblem),
+        (status = 503, description = "No database connection available, retry after the Retry-After delay", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["admin"]),
+        ("api_key" = ["admin"])
+    )
+)]
+#[post("/api-keys")]
+pub async fn add(req: HttpRequest, api_key_form: Json<ApiPayloadApiKey>, pool: web::Data<DbPool>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_ADMIN)?;
+
+    api_key_form.validate()?;
+
+    let api_key_repository = ApiKeyRepository;
+    let api_key_form = api_key_form.into_inner();
+    let key = generate_key();
+    let new_api_key = ApiKey {
+        id: Uuid::new_v4().to_string(),
+        name: api_key_form.name.trim().to_string(),
+        owner: api_key_form.owner.trim().to_string(),
+        key_hash: hash_key(&key),
+        scope: api_key_form.scope.split_whitespace().collect::<Vec<&str>>().join(" "),
+        created_at: Utc::now(),
+        expires_at: api_key_form.expires_at,
+        last_used_at: None,
+        revoked_at: None,
+    };
+    let api_key = new_api_key.clone();
+
+    web::block(move || {
+        let mut conn = pool.get()?;
+
+        Ok::<_, http::error::MyError>(api_key_repository.insert(&new_api_key, &mut conn)?)
+    })
+    .await??;
+
+    Ok(HttpResponse::Created().json(ApiCreatedApiKey { api_key, key }))
+}
+
+#[utoipa::path(
+    path = "/api/api-keys/{api_key_id}",
+    responses(
+        (status = 204, description = "API key revoked, requests made with it are refused"),
+        (status = 403, description = "Token lacks the admin scope", body = ApiProblem),
+        (status = 404, description = "API key not found or already revoked", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["admin"]),
+        ("api_key" = ["admin"])
+    )
+)]
+#[delete("/api-keys/{api_key_id}")]
+pub async fn revoke(req: HttpRequest, path: Path<String>, pool: web::Data<DbPool>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_ADMIN)?;
+
+    let api_key_id = path.into_inner();
+    let api_key_repository = ApiKeyRepository;
+
+    let revoked = web::block(move || {
+        let mut conn = pool.get()?;
+
+        // Kept for the audit, with its last use
+        Ok::<_, http::error::MyError>(api_key_repository.revoke(api_key_id, Utc::now(), &mut conn)?)
+    })
+    .await??;
+
+    match revoked {
+        0 => Err(http::error::MyError::NotFount),
+        _ => Ok(HttpResponse::NoContent().finish()),
+    }
+}
+
+#[actix_web::test]
+async fn test_api_keys() {
+    use actix_web::test;
+    use actix_web::http::StatusCode;
+    use dotenv::dotenv;
+    use actix_web::App;
+    use crate::http::auth::authenticate_api_key;
+    use crate::http::error::MyError;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .wrap_fn(http::auth::grant_scopes("admin"))
+            .service(list)
+            .service(add)
+            .service(revoke)
+    ).await;
+    let owner = format!("batch-{}", Uuid::new_v4());
+
+    let req = test::TestRequest::post().uri("/api-keys")
+        .set_json(ApiPayloadApiKey { name: "nightly import".to_string(), owner: owner.clone(), scope: " quotes:read  quotes:write".to_string(), expires_at: None })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let created: serde_json::Value = test::read_body_json(resp).await;
+    let key = created["key"].as_str().unwrap().to_string();
+    let api_key_id = created["id"].as_str().unwrap().to_string();
+    assert!(created.get("key_hash").is_none());
+
+    let claims = authenticate_api_key(&key, &pool).unwrap();
+    assert_eq!(claims.sub, Some(format!("apikey:{}", api_key_id)));
+    assert!(claims.has_scope("quotes:write") && !claims.has_scope("quotes:delete"));
+    assert!(matches!(authenticate_api_key("rpk_unknown", &pool), Err(MyError::InvalidApiKey)));
+
+    // The key is never listed, only its use
+    let req = test::TestRequest::get().uri("/api-keys").to_request();
+    let listed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let listed = listed.as_array().unwrap().iter().find(|api_key| api_key["id"] == api_key_id.as_str()).unwrap().clone();
+    assert!(listed.get("key").is_none() && listed.get("key_hash").is_none());
+    assert!(listed["last_used_at"].is_string());
+
+    let req = test::TestRequest::delete().uri(&format!("/api-keys/{}", api_key_id)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+    let req = test::TestRequest::delete().uri(&format!("/api-keys/{}", api_key_id)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    assert!(matches!(authenticate_api_key(&key, &pool), Err(MyError::InvalidApiKey)));
+
+    // Expired keys are refused too
+    let req = test::TestRequest::post().uri("/api-keys")
+        .set_json(ApiPayloadApiKey { name: "expired".to_string(), owner, scope: "".to_string(), expires_at: Some(Utc::now() - chrono::Duration::minutes(1)) })
+        .to_request();
+    let created: ApiCreatedApiKey = test::call_and_read_body_json(&app, req).await;
+    assert!(matches!(authenticate_api_key(&created.key, &pool), Err(MyError::InvalidApiKey)));
+}
@@ -0,0 +1,427 @@
+use crate::http;
+use crate::http::auth::{require_scope, ApiRevokeRequest, ApiTokenRequest, Claims, JwtSettings, TokenError, TokenIssuer, SCOPE_ADMIN};
+use crate::http::revocation::RevocationList;
+use crate::http::sessions::{self, ApiLoginRequest, ApiMe, ApiRefreshRequest};
+use crate::db::entities::revoked_token::RevokedToken;
+use crate::db::repositories::revoked_token::RevokedTokenRepository;
+use crate::db::repositories::user::UserRepository;
+use diesel::OptionalExtension;
+use crate::db::pool::DbPool;
+use actix_web::web::{Json, self};
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+use actix_web::{
+    get, post,
+    Result
+};
+use chrono::{DateTime, Utc};
+
+/// Seconds the JWKS may be cached by the verifiers.
+const JWKS_MAX_AGE: u32 = 300;
+
+#[utoipa::path(
+    path = "/auth/token",
+    request_body = ApiTokenRequest,
+    responses(
+        (status = 200, description = "Short-lived bearer token holding the client scopes", body = ApiToken),
+        (status = 400, description = "Invalid payload", body = ApiProblem),
+        (status = 401, description = "Unknown client or wrong secret", body = ApiProblem)
+    )
+)]
+#[post("/token")]
+pub async fn token(credentials: Json<ApiTokenRequest>, issuer: web::Data<TokenIssuer>) -> Result<HttpResponse, http::error::MyError> {
+    if !issuer.authenticates(&credentials) {
+        return Err(http::error::MyError::InvalidClient);
+    }
+
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(issuer.issue()))
+}
+
+#[utoipa::path(
+    path = "/auth/login",
+    request_body = ApiLoginRequest,
+    responses(
+        (status = 200, description = "Short-lived bearer token holding the user scopes, with a refresh token", body = ApiToken),
+        (status = 400, description = "Invalid payload", body = ApiProblem),
+        (status = 401, description = "Unknown user or wrong password", body = ApiProblem),
+        (status = 503, description = "No database connection available, retry after the Retry-After delay", body = ApiProblem)
+    )
+)]
+#[post("/login")]
+pub async fn login(credentials: Json<ApiLoginRequest>, issuer: web::Data<TokenIssuer>, pool: web::Data<DbPool>) -> Result<HttpResponse, http::error::MyError> {
+    let refresh_ttl_seconds = issuer.refresh_ttl_seconds;
+    let (user, refresh_token) = web::block(move || sessions::login(&credentials, refresh_ttl_seconds, &pool)).await??;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(issuer.issue_to(&user.id, &scopes(&user.scope), Some(refresh_token))))
+}
+
+#[utoipa::path(
+    path = "/auth/refresh",
+    request_body = ApiRefreshRequest,
+    responses(
+        (status = 200, description = "Short-lived bearer token holding the user scopes, with the next refresh token", body = ApiToken),
+        (status = 400, description = "Invalid payload", body = ApiProblem),
+        (status = 401, description = "Unknown, expired or revoked refresh token, reusing one revokes the tokens of its login", body = ApiProblem),
+        (status = 503, description = "No database connection available, retry after the Retry-After delay", body = ApiProblem)
+    )
+)]
+#[post("/refresh")]
+pub async fn refresh(payload: Json<ApiRefreshRequest>, issuer: web::Data<TokenIssuer>, pool: web::Data<DbPool>) -> Result<HttpResponse, http::error::MyError> {
+    let refresh_ttl_seconds = issuer.refresh_ttl_seconds;
+    let (user, refresh_token) = web::block(move || sessions::refresh(&payload.refresh_token, refresh_ttl_seconds, &pool)).await??;
+
+    // Scopes read again, changes apply from the next refresh
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .json(issuer.issue_to(&user.id, &scopes(&user.scope), Some(refresh_token))))
+}
+
+fn scopes(scope: &str) -> Vec<String> {
+    scope.split_whitespace().map(str::to_string).collect()
+}
+
+#[utoipa::path(
+    path = "/auth/me",
+    responses(
+        (status = 200, description = "Subject and scopes of the credentials, with the account of users", body = ApiMe),
+        (status = 401, description = "Missing or invalid credentials", body = ApiProblem)
+    ),
+    security(
+        ("token" = []),
+        ("api_key" = [])
+    )
+)]
+#[get("/me")]
+pub async fn me(req: HttpRequest, pool: Option<web::Data<DbPool>>) -> Result<HttpResponse, http::error::MyError> {
+    // Set by the validator
+    let claims = req.extensions().get::<Claims>().cloned().ok_or(http::error::MyError::Internal)?;
+
+    // Users are stored in Postgres only, without it no subject is one
+    let user = match (claims.sub.clone(), pool) {
+        (Some(subject), Some(pool)) => web::block(move || {
+            let mut conn = pool.get()?;
+
+            Ok::<_, http::error::MyError>(UserRepository.get_user(&subject, &mut conn).optional()?)
+        })
+        .await??,
+        _ => None,
+    };
+
+    Ok(HttpResponse::Ok().json(ApiMe { sub: claims.sub, scope: claims.scope, user }))
+}
+
+#[utoipa::path(
+    path = "/auth/revoke",
+    request_body = ApiRevokeRequest,
+    responses(
+        (status = 204, description = "Token revoked, or already expired"),
+        (status = 400, description = "Invalid payload, or a token without jti", body = ApiProblem),
+        (status = 401, description = "Missing or invalid bearer token", body = ApiProblem),
+        (status = 403, description = "The token belongs to another subject and the bearer token lacks the admin scope", body = ApiProblem),
+        (status = 503, description = "No database connection available, retry after the Retry-After delay", body = ApiProblem)
+    ),
+    security(
+        ("token" = []),
+        ("api_key" = [])
+    )
+)]
+#[post("/revoke")]
+pub async fn revoke(
+    req: HttpRequest,
+    payload: Json<ApiRevokeRequest>,
+    jwt: web::Data<JwtSettings>,
+    revocations: web::Data<RevocationList>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, http::error::MyError> {
+    let claims = match jwt.verify(&payload.token) {
+        Ok(claims) => claims,
+        // Refused anyway
+        Err(TokenError::Expired) => return Ok(HttpResponse::NoContent().finish()),
+        Err(err) => return Err(http::error::MyError::InvalidPayload(err.to_string())),
+    };
+
+    // Holders revoke the tokens of their subject, e.g. to log out, admins any token
+    let holder = req.extensions()
+        .get::<Claims>()
+        .is_some_and(|caller| caller.sub.is_some() && caller.sub == claims.sub);
+    if !holder {
+        require_scope(&req, SCOPE_ADMIN)?;
+    }
+
+    let revoked_token = RevokedToken {
+        jti: claims.jti.ok_or_else(|| http::error::MyError::InvalidPayload("The token has no jti claim".to_string()))?,
+        subject: claims.sub,
+        // Verified tokens have an exp claim
+        expires_at: claims.exp.and_then(|expires_at| DateTime::from_timestamp(expires_at, 0)).ok_or(http::error::MyError::Internal)?,
+        revoked_at: Utc::now(),
+    };
+
+    let revoked_token = web::block(move || {
+        let mut conn = pool.get()?;
+        RevokedTokenRepository.insert(&revoked_token, &mut conn)?;
+
+        Ok::<_, http::error::MyError>(revoked_token)
+    })
+    .await??;
+    revocations.revoke(&[revoked_token]);
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "JWK set holding the public key signing the issued tokens, empty while they are signed with the shared secret", body = Object)
+    )
+)]
+#[get("/.well-known/jwks.json")]
+pub async fn jwks(jwt: web::Data<JwtSettings>) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![CacheDirective::Public, CacheDirective::MaxAge(JWKS_MAX_AGE)]))
+        .json(jwt.jwks())
+}
+
+#[actix_web::test]
+async fn test_token() {
+    use actix_web::test;
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use crate::http::auth::{ApiToken, JwtSettings};
+
+    let mut jwt = JwtSettings::from_secret("NOT_A_SECRET");
+    jwt.issuer = "rust-playground".to_string();
+    jwt.audience = "rust-playground".to_string();
+
+    let issuer = TokenIssuer {
+        jwt: jwt.clone(),
+        client_id: "playground".to_string(),
+        client_secret: "client-secret".to_string(),
+        scopes: vec!["admin".to_string()],
+        ttl_seconds: 900,
+        refresh_ttl_seconds: 3600,
+    };
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(issuer))
+            .service(web::scope("/auth").service(token))
+    ).await;
+
+    let req = test::TestRequest::post().uri("/auth/token")
+        .set_json(serde_json::json!({"client_id": "playground", "client_secret": "client-secret"}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("cache-control").unwrap(), "no-store");
+
+    let issued: ApiToken = test::read_body_json(resp).await;
+    assert_eq!(issued.token_type, "Bearer");
+    assert_eq!(issued.expires_in, 900);
+
+    let claims = jwt.verify(&issued.access_token).unwrap();
+    assert_eq!(claims.sub.as_deref(), Some("playground"));
+    assert!(claims.has_scope("admin"));
+    assert_eq!(claims.exp.unwrap() - claims.iat.unwrap(), 900);
+
+    let req = test::TestRequest::post().uri("/auth/token")
+        .set_json(serde_json::json!({"client_id": "playground", "client_secret": "wrong"}))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_token_without_configured_secret() {
+    use actix_web::test;
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use crate::http::auth::JwtSettings;
+
+    let issuer = TokenIssuer {
+        jwt: JwtSettings::from_secret("NOT_A_SECRET"),
+        client_id: "".to_string(),
+        client_secret: "".to_string(),
+        scopes: vec![],
+        ttl_seconds: 900,
+        refresh_ttl_seconds: 3600,
+    };
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(issuer))
+            .service(web::scope("/auth").service(token))
+    ).await;
+
+    let req = test::TestRequest::post().uri("/auth/token")
+        .set_json(serde_json::json!({"client_id": "", "client_secret": ""}))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_jwks() {
+    use actix_web::test;
+    use actix_web::App;
+    use jsonwebtoken::jwk::JwkSet;
+    use crate::http::keys::{Keyring, SigningKey, TEST_KEYS};
+
+    let signing_key = SigningKey::from_pem("rsa-2026", &std::fs::read(format!("{}/rsa.pem", TEST_KEYS)).unwrap()).unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(JwtSettings::new(Keyring::new(signing_key))))
+            .service(jwks)
+    ).await;
+
+    let req = test::TestRequest::get().uri("/.well-known/jwks.json").to_request();
+    let published: JwkSet = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(published.keys.len(), 1);
+    assert!(published.find("rsa-2026").is_some());
+
+    // The shared secret is never published
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(JwtSettings::from_secret("NOT_A_SECRET")))
+            .service(jwks)
+    ).await;
+    let req = test::TestRequest::get().uri("/.well-known/jwks.json").to_request();
+    let published: JwkSet = test::call_and_read_body_json(&app, req).await;
+    assert!(published.keys.is_empty());
+}
+
+#[actix_web::test]
+async fn test_revoke() {
+    use actix_web::test;
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use dotenv::dotenv;
+    use uuid::Uuid;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let jwt = JwtSettings::from_secret("NOT_A_SECRET");
+    let revocations = web::Data::new(RevocationList::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool))
+            .app_data(web::Data::new(jwt.clone()))
+            .app_data(revocations.clone())
+            .service(web::scope("/auth").service(revoke))
+    ).await;
+
+    let subject = format!("batch-{}", Uuid::new_v4());
+    let issued = jwt.sign(&subject, &["quotes:read".to_string()], 600);
+    let caller = |sub: &str, scope: &str| Claims { sub: Some(sub.to_string()), scope: scope.to_string(), ..Claims::default() };
+    let revoke_request = |caller: Claims, revoked: &str| {
+        let req = test::TestRequest::post().uri("/auth/revoke")
+            .set_json(ApiRevokeRequest { token: revoked.to_string() })
+            .to_request();
+        req.extensions_mut().insert(caller);
+        req
+    };
+
+    // Only the holder or an admin
+    let resp = test::call_service(&app, revoke_request(caller("someone", "quotes:read"), &issued)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert!(revocations.check(jwt.verify(&issued).unwrap()).is_ok());
+
+    let resp = test::call_service(&app, revoke_request(caller(&subject, "quotes:read"), &issued)).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert_eq!(revocations.check(jwt.verify(&issued).unwrap()), Err(TokenError::Revoked));
+
+    let other = jwt.sign(&subject, &[], 600);
+    let resp = test::call_service(&app, revoke_request(caller("admin", "admin"), &other)).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert_eq!(revocations.check(jwt.verify(&other).unwrap()), Err(TokenError::Revoked));
+
+    // Revoked twice, or expired: nothing to do
+    let resp = test::call_service(&app, revoke_request(caller(&subject, ""), &issued)).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let expired = jwt.sign(&subject, &[], -(jwt.leeway_seconds + 60));
+    let resp = test::call_service(&app, revoke_request(caller(&subject, ""), &expired)).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = test::call_service(&app, revoke_request(caller(&subject, ""), "not-a-token")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_login_and_me() {
+    use actix_web::test;
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use dotenv::dotenv;
+    use uuid::Uuid;
+    use crate::http::auth::ApiToken;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let username = format!("castafiore-{}", Uuid::new_v4());
+    let user = crate::cli::users::create_user(&pool, &username, "Ah je ris de me voir si belle", &["quotes:read".to_string()]).unwrap();
+
+    let jwt = JwtSettings::from_secret("NOT_A_SECRET");
+    let issuer = TokenIssuer {
+        jwt: jwt.clone(),
+        client_id: "playground".to_string(),
+        client_secret: "".to_string(),
+        scopes: vec![],
+        ttl_seconds: 900,
+        refresh_ttl_seconds: 3600,
+    };
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool))
+            .app_data(web::Data::new(issuer))
+            .service(web::scope("/auth").service(login).service(refresh).service(me))
+    ).await;
+
+    let req = test::TestRequest::post().uri("/auth/login")
+        .set_json(ApiLoginRequest { username: username.clone(), password: "Ah je ris".to_string() })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::post().uri("/auth/login")
+        .set_json(ApiLoginRequest { username: username.clone(), password: "Ah je ris de me voir si belle".to_string() })
+        .to_request();
+    let logged_in: ApiToken = test::call_and_read_body_json(&app, req).await;
+    let claims = jwt.verify(&logged_in.access_token).unwrap();
+    assert_eq!(claims.sub.as_deref(), Some(user.id.as_str()));
+    assert!(claims.has_scope("quotes:read") && !claims.has_scope("quotes:write"));
+
+    let req = test::TestRequest::post().uri("/auth/refresh")
+        .set_json(ApiRefreshRequest { refresh_token: logged_in.refresh_token.unwrap() })
+        .to_request();
+    let refreshed: ApiToken = test::call_and_read_body_json(&app, req).await;
+    assert!(refreshed.refresh_token.is_some());
+
+    let req = test::TestRequest::get().uri("/auth/me").to_request();
+    req.extensions_mut().insert(jwt.verify(&refreshed.access_token).unwrap());
+    let found: ApiMe = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(found.user.unwrap().username, username);
+    assert_eq!(found.scope, "quotes:read");
+
+    let req = test::TestRequest::get().uri("/auth/me").to_request();
+    req.extensions_mut().insert(Claims { sub: Some("batch-import".to_string()), ..Claims::default() });
+    let found: ApiMe = test::call_and_read_body_json(&app, req).await;
+    assert!(found.user.is_none());
+}
+
+#[actix_web::test]
+async fn test_me_without_postgres() {
+    use actix_web::test;
+    use actix_web::App;
+
+    let app = test::init_service(
+        App::new().service(web::scope("/auth").service(me))
+    ).await;
+
+    let req = test::TestRequest::get().uri("/auth/me").to_request();
+    req.extensions_mut().insert(Claims { sub: Some("milou".to_string()), scope: "quotes:read".to_string(), ..Claims::default() });
+    let found: ApiMe = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(found.sub.as_deref(), Some("milou"));
+    assert!(found.user.is_none());
+}
@@ -0,0 +1,299 @@
+use crate::http;
+use crate::http::auth::{require_scope, SCOPE_QUOTES_DELETE, SCOPE_QUOTES_READ, SCOPE_QUOTES_WRITE};
+use crate::db::store::QuoteStore;
+use crate::db::entities::author::{Author, ApiPayloadAuthor, ApiAuthorListParams, ApiAuthorPage, slugify};
+use validator::Validate;
+use actix_web::web::{Path, Json, Query, self};
+use actix_web::{HttpRequest, HttpResponse};
+use actix_web::{
+    get, delete, post, put,
+    Result
+};
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+#[utoipa::path(
+    path = "/api/authors",
+    params(ApiAuthorListParams),
+    responses(
+        (status = 200, description = "Page of authors ordered by slug", body = ApiAuthorPage),
+        (status = 403, description = "Token lacks the quotes:read scope", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["quotes:read"]),
+        ("api_key" = ["quotes:read"])
+    )
+)]
+#[get("/authors")]
+pub async fn list(req: HttpRequest, params: Query<ApiAuthorListParams>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_READ)?;
+
+    let params = params.into_inner();
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    // Fetch one extra row to know whether another page follows
+    let authors = web::block(move || store.list_authors(limit + 1, params.cursor)).await??;
+
+    let mut items = authors;
+    let next_cursor = if items.len() as i64 > limit {
+        items.truncate(limit as usize);
+        items.last().map(|author| author.slug.clone())
+    } else {
+        None
+    };
+
+    Ok(HttpResponse::Ok().json(ApiAuthorPage { items, next_cursor }))
+}
+
+#[utoipa::path(
+    path = "/api/authors/{author_id}",
+    responses(
+        (status = 200, description = "Author found", body = Author),
+        (status = 403, description = "Token lacks the quotes:read scope", body = ApiProblem),
+        (status = 404, description = "Author not found", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["quotes:read"]),
+        ("api_key" = ["quotes:read"])
+    )
+)]
+#[get("/authors/{author_id}")]
+pub async fn item(req: HttpRequest, path: Path<String>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_READ)?;
+
+    let author_id = path.into_inner();
+
+    let author = web::block(move || store.get_author(&author_id)).await??;
+
+    Ok(HttpResponse::Ok().json(author))
+}
+
+#[utoipa::path(
+    path = "/api/authors/{author_id}",
+    responses(
+        (status = 204, description = "Author deleted"),
+        (status = 403, description = "Token lacks the quotes:delete scope", body = ApiProblem),
+        (status = 404, description = "Author not found", body = ApiProblem),
+        (status = 409, description = "Author still has quotes", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["quotes:delete"]),
+        ("api_key" = ["quotes:delete"])
+    )
+)]
+#[delete("/authors/{author_id}")]
+pub async fn delete(req: HttpRequest, path: Path<String>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_DELETE)?;
+
+    let author_id = path.into_inner();
+
+    web::block(move || store.delete_author(&author_id)).await??;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(
+    path = "/api/authors",
+    request_body = ApiPayloadAuthor,
+    responses(
+        (status = 201, description = "Author created successfully", body = Author),
+        (status = 403, description = "Token lacks the quotes:write scope", body = ApiProblem),
+        (status = 422, description = "Validation error", body = ApiProblem),
+        (status = 409, description = "An author with the same name already exists", body = ApiProblem),
+        (status = 503, description = "No database connection available, retry after the Retry-After delay", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["quotes:write"]),
+        ("api_key" = ["quotes:write"])
+    )
+)]
+#[post("/authors")]
+pub async fn add(req: HttpRequest, author_form: Json<ApiPayloadAuthor>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_WRITE)?;
+
+    author_form.validate()?;
+
+    let author_form = author_form.into_inner();
+    let new_author = Author {
+        id: Uuid::new_v4().to_string(),
+        slug: slugify(&author_form.name),
+        name: author_form.name.trim().to_string(),
+        bio: author_form.bio,
+        birth_date: author_form.birth_date,
+        death_date: author_form.death_date,
+    };
+
+    let author = web::block(move || store.create_author(new_author)).await??;
+
+    Ok(HttpResponse::Created().json(author))
+}
+
+#[utoipa::path(
+    path = "/api/authors/{author_id}",
+    request_body = ApiPayloadAuthor,
+    responses(
+        (status = 200, description = "Author updated successfully", body = Author),
+        (status = 403, description = "Token lacks the quotes:write scope", body = ApiProblem),
+        (status = 422, description = "Validation error", body = ApiProblem),
+        (status = 404, description = "Author not found", body = ApiProblem),
+        (status = 409, description = "Another author already has the same name", body = ApiProblem),
+        (status = 503, description = "No database connection available, retry after the Retry-After delay", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["quotes:write"]),
+        ("api_key" = ["quotes:write"])
+    )
+)]
+#[put("/authors/{author_id}")]
+pub async fn update(req: HttpRequest, author_form: Json<ApiPayloadAuthor>, path: Path<String>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_WRITE)?;
+
+    let author_id = path.into_inner();
+
+    author_form.validate()?;
+
+    let author_form = author_form.into_inner();
+
+    let author = web::block(move || {
+        let mut db_author = store.get_author(&author_id)?;
+
+        db_author.slug = slugify(&author_form.name);
+        db_author.name = author_form.name.trim().to_string();
+        db_author.bio = author_form.bio;
+        db_author.birth_date = author_form.birth_date;
+        db_author.death_date = author_form.death_date;
+
+        store.update_author(db_author)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(author))
+}
+
+#[actix_web::test]
+async fn test_get_list() {
+    use actix_web::test;
+    use actix_web::http::header::ContentType;
+    use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, memory::MemoryQuoteStore};
+
+    let store: SharedQuoteStore = Arc::new(MemoryQuoteStore::default());
+    for name in ["Tintin", "Milou", "Capitaine Haddock"] {
+        store.create_author(Author { id: Uuid::new_v4().to_string(), name: name.to_string(), slug: slugify(name), bio: None, birth_date: None, death_date: None }).unwrap();
+    }
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::authors::list)
+    ).await;
+    let req = test::TestRequest::get().uri("/authors?limit=2")
+        .insert_header(ContentType::json())
+        .to_request();
+    let page: ApiAuthorPage = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(page.items.iter().map(|author| author.slug.as_str()).collect::<Vec<&str>>(), vec!["capitaine-haddock", "milou"]);
+    assert_eq!(page.next_cursor, Some("milou".to_string()));
+}
+
+#[actix_web::test]
+async fn test_crud() {
+    use actix_web::test;
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::ContentType;
+    use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, memory::MemoryQuoteStore};
+
+    let store: SharedQuoteStore = Arc::new(MemoryQuoteStore::default());
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::authors::item)
+            .service(http::controllers::authors::add)
+            .service(http::controllers::authors::update)
+            .service(http::controllers::authors::delete)
+    ).await;
+    let name = format!("Bianca Castafiore {}", Uuid::new_v4());
+
+    let req = test::TestRequest::post().uri("/authors")
+        .insert_header(ContentType::json())
+        .set_json(ApiPayloadAuthor { name: name.clone(), bio: None, birth_date: None, death_date: None })
+        .to_request();
+    let created: Author = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(created.slug, slugify(&name));
+
+    let req = test::TestRequest::post().uri("/authors")
+        .insert_header(ContentType::json())
+        .set_json(ApiPayloadAuthor { name: format!(" {} ", name.to_uppercase()), bio: None, birth_date: None, death_date: None })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    let req = test::TestRequest::put().uri(&format!("/authors/{}", created.id))
+        .insert_header(ContentType::json())
+        .set_json(ApiPayloadAuthor { name: name.clone(), bio: Some("Le rossignol milanais".to_string()), birth_date: None, death_date: None })
+        .to_request();
+    let updated: Author = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(updated.bio, Some("Le rossignol milanais".to_string()));
+
+    let req = test::TestRequest::delete().uri(&format!("/authors/{}", created.id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::get().uri(&format!("/authors/{}", created.id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_scopes() {
+    use actix_web::test;
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::ContentType;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, memory::MemoryQuoteStore};
+    use crate::http::error::ApiProblem;
+
+    let store: SharedQuoteStore = Arc::new(MemoryQuoteStore::default());
+    let author = store.create_author(Author { id: Uuid::new_v4().to_string(), name: "Tryphon Tournesol".to_string(), slug: slugify("Tryphon Tournesol"), bio: None, birth_date: None, death_date: None }).unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::authors::item)
+            .service(http::controllers::authors::add)
+            .service(http::controllers::authors::delete)
+    ).await;
+
+    let req = test::TestRequest::get().uri(&format!("/authors/{}", author.id)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post().uri("/authors")
+        .insert_header(ContentType::json())
+        .set_json(ApiPayloadAuthor { name: "Nestor".to_string(), bio: None, birth_date: None, death_date: None })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let problem: ApiProblem = test::read_body_json(resp).await;
+    assert_eq!(problem.detail.as_deref(), Some("The token does not grant the quotes:write scope"));
+
+    let req = test::TestRequest::delete().uri(&format!("/authors/{}", author.id)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+    assert!(store.get_author(&author.id).is_ok());
+}
@@ -1 +1,4 @@
 pub mod quotes;
+pub mod authors;
+pub mod auth;
+pub mod api_keys;
@@ -1,222 +1,526 @@
-use crate::http::{self, error};
-use crate::db::repositories::quote::QuoteRepository;
-use crate::db::entities::quote::{Quote, ApiPayloadQuote};
-use actix_web::http::StatusCode;
-use actix_web::http::header::ContentType;
+use crate::http;
+use crate::http::auth::{require_scope, Claims, SCOPE_ADMIN, SCOPE_QUOTES_DELETE, SCOPE_QUOTES_READ, SCOPE_QUOTES_WRITE};
+use crate::http::etag::{self, quote_etag};
+use crate::http::patch::QuotePatch;
+use crate::db::repositories::quote::{QuoteCursor, QuoteFilters, QuoteSort};
+use crate::db::store::QuoteStore;
+use crate::db::entities::quote::{ApiPayloadQuote, ApiQuoteListParams, ApiQuoteItemParams, ApiQuotePage, QuoteSortField, ApiQuoteSearchParams, ApiQuoteRandomParams};
+use crate::db::entities::daily_quote::{DailyQuote, ApiPayloadDailyQuote};
+use crate::db::entities::quote_revision::ApiRevisionDiffParams;
+use chrono::Utc;
+use actix_web::http::header::ETag;
 use validator::Validate;
-use crate::db::pool::DbPool;
-use actix_web::web::{Path, Json, self};
-use actix_web::HttpResponse;
-use actix_web::Responder;
+use actix_web::web::{Path, Json, Query, self};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
 use actix_web::{
-    get, delete, post, put,
+    get, delete, patch, post, put,
     Result
 };
-use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+const DEFAULT_SEARCH_SIZE: i64 = 20;
+const MAX_SEARCH_SIZE: i64 = 100;
+
+/// Every value of a repeatable query parameter such as `?tag=a&tag=b`.
+fn query_values(req: &HttpRequest, name: &str) -> Vec<String> {
+    Query::<Vec<(String, String)>>::from_query(req.query_string())
+        .map(|pairs| {
+            pairs
+                .into_inner()
+                .into_iter()
+                .filter(|(key, _)| key == name)
+                .map(|(_, value)| value)
+                .collect()
+        })
+        .unwrap_or_default()
+}
 
 #[utoipa::path(
     path = "/api/quotes",
+    params(
+        ApiQuoteListParams,
+        ("tag" = Option<Vec<String>>, Query, description = "Only list quotes with these tags, repeat the parameter for several tags")
+    ),
     responses(
-        (status = 200, description = "List current quote items", body = [Quote])
+        (status = 200, description = "Page of quotes in the requested order", body = ApiQuotePage),
+        (status = 400, description = "Malformed cursor", body = ApiProblem),
+        (status = 403, description = "Token lacks the quotes:read scope, or the admin scope to include deleted quotes", body = ApiProblem)
     ),
     security(
-        ("token" = [])
+        ("token" = ["quotes:read"]),
+        ("api_key" = ["quotes:read"])
     )
 )]
 #[get("/quotes")]
-pub async fn list(pool: web::Data<DbPool>) -> actix_web::Result<HttpResponse, http::error::MyError> {
-    let quote_repository = QuoteRepository;
+pub async fn list(req: HttpRequest, params: Query<ApiQuoteListParams>, store: web::Data<dyn QuoteStore>) -> actix_web::Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_READ)?;
 
-    let quotes = web::block(move || {
-        let mut conn = pool.get().expect("couldn't get db connection from pool");
+    let params = params.into_inner();
+    let include_deleted = params.include_deleted.unwrap_or(false);
 
-        quote_repository.get_quotes(
-            Some(1000),
-            &mut conn
-        )
-    })
-    .await;
+    if include_deleted {
+        require_scope(&req, SCOPE_ADMIN)?;
+    }
+
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let filters = QuoteFilters {
+        tags: query_values(&req, "tag"),
+        tag_mode: params.tag_mode.unwrap_or_default(),
+        updated_since: params.updated_since,
+        include_deleted,
+        ..QuoteFilters::default()
+    };
+    let sort = QuoteSort {
+        field: params.sort.unwrap_or_default(),
+        order: params.order.unwrap_or_default(),
+    };
+    let cursor = match params.cursor.as_deref().map(QuoteCursor::decode) {
+        Some(None) => return Err(http::error::MyError::BadClientData),
+        Some(Some(cursor)) if cursor.timestamp.is_none() != (sort.field == QuoteSortField::Id) => {
+            // The cursor was produced for another sort field
+            return Err(http::error::MyError::BadClientData);
+        },
+        decoded => decoded.flatten(),
+    };
+
+    // Fetch one extra quote to know whether another page follows
+    let mut items = web::block(move || store.list_quotes(limit + 1, cursor, sort, &filters)).await??;
+
+    let next_cursor = if items.len() as i64 > limit {
+        items.truncate(limit as usize);
+        items.last().map(|last_quote| QuoteCursor::after(&last_quote.quote, sort).encode())
+    } else {
+        None
+    };
+
+    Ok(HttpResponse::Ok().json(ApiQuotePage { items, next_cursor }))
+}
+
+#[utoipa::path(
+    path = "/api/quotes/search",
+    params(ApiQuoteSearchParams),
+    responses(
+        (status = 200, description = "Quotes matching the search terms, most relevant first", body = [ApiQuote]),
+        (status = 400, description = "Empty search terms", body = ApiProblem),
+        (status = 403, description = "Token lacks the quotes:read scope", body = ApiProblem),
+        (status = 503, description = "No database connection available, retry after the Retry-After delay", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["quotes:read"]),
+        ("api_key" = ["quotes:read"])
+    )
+)]
+#[get("/quotes/search")]
+pub async fn search(req: HttpRequest, params: Query<ApiQuoteSearchParams>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_READ)?;
 
-    match quotes {
-        Ok(_) => Ok(HttpResponse::Ok().json(quotes.unwrap().unwrap())),
-        Err(_) => Err(http::error::MyError::NotFount),
+    let params = params.into_inner();
+
+    if params.q.trim().is_empty() {
+        return Err(http::error::MyError::BadClientData);
     }
+
+    let limit = params.limit.unwrap_or(DEFAULT_SEARCH_SIZE).clamp(1, MAX_SEARCH_SIZE);
+
+    let quotes = web::block(move || store.search_quotes(&params.q, limit)).await??;
+
+    Ok(HttpResponse::Ok().json(quotes))
+}
+
+#[utoipa::path(
+    path = "/api/quotes/random",
+    params(ApiQuoteRandomParams),
+    responses(
+        (status = 200, description = "A quote drawn at random", body = ApiQuote),
+        (status = 403, description = "Token lacks the quotes:read scope", body = ApiProblem),
+        (status = 404, description = "No quote matches the filters", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["quotes:read"]),
+        ("api_key" = ["quotes:read"])
+    )
+)]
+#[get("/quotes/random")]
+pub async fn random(req: HttpRequest, params: Query<ApiQuoteRandomParams>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_READ)?;
+
+    let params = params.into_inner();
+    let filters = QuoteFilters {
+        author: params.author,
+        tags: params.tag.into_iter().collect(),
+        ..QuoteFilters::default()
+    };
+
+    let quote = web::block(move || store.random_quote(&filters)).await??;
+
+    Ok(HttpResponse::Ok().json(quote))
+}
+
+#[utoipa::path(
+    path = "/api/quotes/daily",
+    responses(
+        (status = 200, description = "Quote of the current UTC day, the same for every caller", body = ApiQuote),
+        (status = 403, description = "Token lacks the quotes:read scope", body = ApiProblem),
+        (status = 404, description = "No quote available", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["quotes:read"]),
+        ("api_key" = ["quotes:read"])
+    )
+)]
+#[get("/quotes/daily")]
+pub async fn daily(req: HttpRequest, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_READ)?;
+
+    let today = Utc::now().date_naive();
+
+    let quote = web::block(move || store.daily_quote(today)).await??;
+
+    Ok(HttpResponse::Ok().json(quote))
+}
+
+#[utoipa::path(
+    path = "/api/quotes/daily",
+    request_body = ApiPayloadDailyQuote,
+    responses(
+        (status = 200, description = "Quote pinned as quote of the day", body = DailyQuote),
+        (status = 403, description = "Token lacks the admin scope", body = ApiProblem),
+        (status = 404, description = "Quote not found", body = ApiProblem),
+        (status = 422, description = "Validation error", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["admin"]),
+        ("api_key" = ["admin"])
+    )
+)]
+#[put("/quotes/daily")]
+pub async fn pin_daily(req: HttpRequest, daily_form: Json<ApiPayloadDailyQuote>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_ADMIN)?;
+
+    daily_form.validate()?;
+
+    let daily_quote = DailyQuote {
+        day: daily_form.day.unwrap_or_else(|| Utc::now().date_naive()),
+        quote_id: daily_form.quote_id.to_string(),
+        pinned: true,
+    };
+
+    let daily_quote = web::block(move || store.pin_daily_quote(daily_quote)).await??;
+
+    Ok(HttpResponse::Ok().json(daily_quote))
 }
 
 #[utoipa::path(
     path = "/api/quotes/{quote_id}",
+    params(ApiQuoteItemParams),
     responses(
-        (status = 200, description = "Quote created successfully", body = Quote),
-        (status = 404, description = "Quote not found")
+        (status = 200, description = "Quote found", body = ApiQuote, headers(("ETag" = String, description = "Current version of the quote"))),
+        (status = 304, description = "Quote unchanged since the `If-None-Match` ETag"),
+        (status = 403, description = "Token lacks the quotes:read scope, or the admin scope to include deleted quotes", body = ApiProblem),
+        (status = 404, description = "Quote not found", body = ApiProblem)
     ),
     security(
-        ("token" = [])
+        ("token" = ["quotes:read"]),
+        ("api_key" = ["quotes:read"])
     )
 )]
 #[get("/quotes/{quote_id}")]
-pub async fn item(path: Path<String>, pool: web::Data<DbPool>) -> Result<HttpResponse, http::error::MyError> {
+pub async fn item(req: HttpRequest, path: Path<String>, params: Query<ApiQuoteItemParams>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_READ)?;
+
     let quote_id = path.into_inner();
-    let quote_repository = QuoteRepository;
+    let include_deleted = params.into_inner().include_deleted.unwrap_or(false);
 
-    let quote = web::block(move || {
-        let mut conn = pool.get().expect("couldn't get db connection from pool");
+    if include_deleted {
+        require_scope(&req, SCOPE_ADMIN)?;
+    }
 
-        quote_repository.get_quote(quote_id, &mut conn)
-    })
-    .await;
+    let quote = web::block(move || store.get_quote(&quote_id, include_deleted)).await??;
 
-    match quote {
-        Ok(_) => Ok(HttpResponse::Ok().json(quote.unwrap().unwrap())),
-        Err(_) => Err(http::error::MyError::NotFount),
+    if etag::is_not_modified(&req, &quote.quote) {
+        return Ok(HttpResponse::NotModified()
+            .insert_header(ETag(quote_etag(&quote.quote)))
+            .finish());
     }
+
+    Ok(HttpResponse::Ok()
+        .insert_header(ETag(quote_etag(&quote.quote)))
+        .json(quote))
 }
 
 #[utoipa::path(
     path = "/api/quotes/{quote_id}",
     responses(
-        (status = 200, description = "Quote deleted"),
+        (status = 204, description = "Quote deleted, it can be restored until purged"),
+        (status = 403, description = "Token lacks the quotes:delete scope", body = ApiProblem),
+        (status = 404, description = "Quote not found", body = ApiProblem),
+        (status = 412, description = "Quote changed since the `If-Match` ETag", body = ApiProblem)
     ),
     security(
-        ("token" = [])
+        ("token" = ["quotes:delete"]),
+        ("api_key" = ["quotes:delete"])
     )
 )]
 #[delete("/quotes/{quote_id}")]
-pub async fn delete(path: Path<String>, pool: web::Data<DbPool>) -> impl Responder {
+pub async fn delete(req: HttpRequest, path: Path<String>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_DELETE)?;
+
     let quote_id = path.into_inner();
-    let quote_repository = QuoteRepository;
+    let if_match = etag::if_match(&req)?;
 
-    let _ = web::block(move || {
-        let mut conn = pool.get().expect("couldn't get db connection from pool");
+    web::block(move || {
+        let expected_version = match &if_match {
+            Some(_) => etag::expected_version(&if_match, &store.get_quote(&quote_id, false)?.quote)?,
+            None => None,
+        };
 
-        quote_repository.remove(quote_id, &mut conn)
+        store.delete_quote(&quote_id, expected_version)
     })
-    .await;
+    .await??;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(
+    path = "/api/quotes/{quote_id}/restore",
+    responses(
+        (status = 200, description = "Quote restored", body = ApiQuote),
+        (status = 403, description = "Token lacks the admin scope", body = ApiProblem),
+        (status = 404, description = "No deleted quote with this id", body = ApiProblem),
+    ),
+    security(
+        ("token" = ["admin"]),
+        ("api_key" = ["admin"])
+    )
+)]
+#[post("/quotes/{quote_id}/restore")]
+pub async fn restore(req: HttpRequest, path: Path<String>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_ADMIN)?;
+
+    let quote_id = path.into_inner();
 
-    HttpResponse::Ok()
+    let quote = web::block(move || store.restore_quote(&quote_id)).await??;
+
+    Ok(HttpResponse::Ok().json(quote))
 }
 
 #[utoipa::path(
     path = "/api/quotes",
     request_body = ApiPayloadQuote,
     responses(
-        (status = 201, description = "Quote created successfully", body = Quote),
-        (status = 406, description = "Validation error", body = ValidationErrors),
-        (status = 503, description = "Server error")
+        (status = 201, description = "Quote created successfully", body = ApiQuote, headers(("ETag" = String, description = "Current version of the quote"))),
+        (status = 403, description = "Token lacks the quotes:write scope", body = ApiProblem),
+        (status = 422, description = "Validation error", body = ApiProblem),
+        (status = 503, description = "No database connection available, retry after the Retry-After delay", body = ApiProblem)
     ),
     security(
-        ("token" = [])
+        ("token" = ["quotes:write"]),
+        ("api_key" = ["quotes:write"])
     )
 )]
 #[post("/quotes")]
-pub async fn add(quote_form: Json<ApiPayloadQuote>, pool: web::Data<DbPool>) -> Result<HttpResponse, http::error::MyError> {
-    let quote_repository = QuoteRepository;
+pub async fn add(req: HttpRequest, quote_form: Json<ApiPayloadQuote>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_WRITE)?;
 
-    let validation = quote_form.validate();
+    quote_form.validate()?;
 
-    if validation.is_err() {
-        return Ok(HttpResponse::build(StatusCode::NOT_ACCEPTABLE)
-            .insert_header(ContentType::json())
-            .json(validation.err()));
-    }
+    let created_by = req.extensions().get::<Claims>().and_then(|claims| claims.sub.clone());
 
-    let id = Uuid::new_v4().to_string();
-    let new_quote = Quote {
-        id: id.clone(),
-        author: quote_form.author.to_string(),
-        quote: quote_form.quote.to_string()
-    };
-    let result_quote = new_quote.clone();
+    let quote = web::block(move || store.create_quote(&quote_form, created_by.as_deref())).await??;
+
+    Ok(HttpResponse::Created()
+        .insert_header(ETag(quote_etag(&quote.quote)))
+        .json(quote))
+}
+
+#[utoipa::path(
+    path = "/api/quotes/{quote_id}",
+    request_body = ApiPayloadQuote,
+    responses(
+        (status = 200, description = "Quote updated successfully", body = ApiQuote, headers(("ETag" = String, description = "Current version of the quote"))),
+        (status = 403, description = "Token lacks the quotes:write scope", body = ApiProblem),
+        (status = 422, description = "Validation error", body = ApiProblem),
+        (status = 404, description = "Quote not found", body = ApiProblem),
+        (status = 412, description = "Quote changed since the `If-Match` ETag", body = ApiProblem),
+        (status = 503, description = "No database connection available, retry after the Retry-After delay", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["quotes:write"]),
+        ("api_key" = ["quotes:write"])
+    )
+)]
+#[put("/quotes/{quote_id}")]
+pub async fn update(req: HttpRequest, quote_form: Json<ApiPayloadQuote>, path: Path<String>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_WRITE)?;
 
-    let quote_insert = web::block(move || {
-        let mut conn = pool.get().expect("couldn't get db connection from pool");
+    let quote_id = path.into_inner();
+    let if_match = etag::if_match(&req)?;
 
-        quote_repository
-            .insert(new_quote.clone(), &mut conn)
+    quote_form.validate()?;
+
+    let quote = web::block(move || {
+        let expected_version = etag::expected_version(&if_match, &store.get_quote(&quote_id, false)?.quote)?;
+
+        store.update_quote(&quote_id, &quote_form, expected_version)
     })
-    .await;
+    .await??;
 
-    match quote_insert {
-        Ok(_) => Ok(HttpResponse::Ok().json(result_quote)),
-        Err(_) => Err(http::error::MyError::ServerUnavailable),
-    }
+    Ok(HttpResponse::Ok()
+        .insert_header(ETag(quote_etag(&quote.quote)))
+        .json(quote))
 }
 
 #[utoipa::path(
     path = "/api/quotes/{quote_id}",
-    request_body = ApiPayloadQuote,
+    request_body(
+        content = ApiPayloadQuote,
+        content_type = "application/merge-patch+json",
+        description = "Merge patch (RFC 7396) of the quote fields, any field may be omitted. \
+            RFC 6902 operations on the same fields are accepted as `application/json-patch+json`."
+    ),
     responses(
-        (status = 201, description = "Quote created successfully", body = Quote),
-        (status = 406, description = "Validation error", body = ValidationErrors),
-        (status = 404, description = "Quote not found"),
-        (status = 503, description = "Server error")
+        (status = 200, description = "Quote patched successfully", body = ApiQuote, headers(("ETag" = String, description = "Current version of the quote"))),
+        (status = 400, description = "Malformed patch or patch not applicable", body = ApiProblem),
+        (status = 403, description = "Token lacks the quotes:write scope", body = ApiProblem),
+        (status = 404, description = "Quote not found", body = ApiProblem),
+        (status = 422, description = "Validation error of the patched quote", body = ApiProblem),
+        (status = 412, description = "Quote changed since the `If-Match` ETag, or while being patched", body = ApiProblem),
+        (status = 415, description = "Neither a merge patch nor a JSON patch", body = ApiProblem),
+        (status = 503, description = "No database connection available, retry after the Retry-After delay", body = ApiProblem)
     ),
     security(
-        ("token" = [])
+        ("token" = ["quotes:write"]),
+        ("api_key" = ["quotes:write"])
     )
 )]
-#[put("/quotes/{quote_id}")]
-pub async fn update(quote_form: Json<ApiPayloadQuote>, path: Path<String>, pool: web::Data<crate::db::pool::DbPool>) -> Result<HttpResponse, http::error::MyError> {
+#[patch("/quotes/{quote_id}")]
+pub async fn patch(req: HttpRequest, body: web::Bytes, path: Path<String>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_WRITE)?;
+
     let quote_id = path.into_inner();
+    let if_match = etag::if_match(&req)?;
+    let quote_patch = QuotePatch::parse(&req, &body)?;
 
-    let validation = quote_form.validate();
+    let reader = store.clone();
+    let current = web::block(move || reader.get_quote(&quote_id, false)).await??;
 
-    if validation.is_err() {
-        return Ok(HttpResponse::build(StatusCode::NOT_ACCEPTABLE)
-            .insert_header(ContentType::json())
-            .json(validation.err()));
+    let mut quote_form = quote_patch.apply(&current)?;
+
+    quote_form.validate()?;
+
+    // Leave the tags alone when the patch did not change them
+    if quote_form.tags.as_ref() == Some(&current.tags) {
+        quote_form.tags = None;
     }
 
-    let quote_repository = QuoteRepository;
+    // The patch was applied to the version read, a write landing meanwhile fails it even without `If-Match`
+    etag::expected_version(&if_match, &current.quote)?;
+    let expected_version = Some(current.quote.version);
 
-    let quote_update = web::block(move || {
-        let mut conn = pool.get().expect("couldn't get db connection from pool");
+    let quote = web::block(move || store.update_quote(&current.quote.id, &quote_form, expected_version)).await??;
 
-        let quote_promise = quote_repository.get_quote(quote_id, &mut conn);
+    Ok(HttpResponse::Ok()
+        .insert_header(ETag(quote_etag(&quote.quote)))
+        .json(quote))
+}
 
-        if quote_promise.is_err() {
-            return Err(error::MyError::NotFount);
-        }
+#[utoipa::path(
+    path = "/api/quotes/{quote_id}/revisions",
+    responses(
+        (status = 200, description = "Revisions of the quote, oldest first", body = [QuoteRevision]),
+        (status = 403, description = "Token lacks the quotes:read scope", body = ApiProblem),
+        (status = 404, description = "Quote not found", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["quotes:read"]),
+        ("api_key" = ["quotes:read"])
+    )
+)]
+#[get("/quotes/{quote_id}/revisions")]
+pub async fn revisions(req: HttpRequest, path: Path<String>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_READ)?;
+
+    let quote_id = path.into_inner();
 
-        let mut db_quote = quote_promise.unwrap().clone();
-        db_quote.quote = quote_form.quote.to_string();
-        db_quote.author = quote_form.author.to_string();
+    let history = web::block(move || store.quote_revisions(&quote_id)).await??;
+
+    Ok(HttpResponse::Ok().json(history))
+}
+
+#[utoipa::path(
+    path = "/api/quotes/{quote_id}/revisions/diff",
+    params(ApiRevisionDiffParams),
+    responses(
+        (status = 200, description = "Fields changed between the two revisions", body = ApiRevisionDiff),
+        (status = 403, description = "Token lacks the quotes:read scope", body = ApiProblem),
+        (status = 404, description = "Quote or revision not found", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["quotes:read"]),
+        ("api_key" = ["quotes:read"])
+    )
+)]
+#[get("/quotes/{quote_id}/revisions/diff")]
+pub async fn revision_diff(req: HttpRequest, path: Path<String>, params: Query<ApiRevisionDiffParams>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_READ)?;
+
+    let quote_id = path.into_inner();
+    let params = params.into_inner();
 
-        let update_promise = quote_repository.update(
-            db_quote.clone(),
-            &mut conn
-        );
+    let diff = web::block(move || {
+        let from = store.quote_revision(&quote_id, params.from)?;
+        let to = store.quote_revision(&quote_id, params.to)?;
 
-        match update_promise {
-            Ok(_) => Ok(update_promise.unwrap()),
-            Err(_) => Err(http::error::MyError::BadClientData),
-        }        
+        Ok::<_, http::error::MyError>(from.diff(&to))
     })
-    .await;
-
-    match quote_update {
-        Ok(_) => Ok(
-            HttpResponse::Ok().json(
-                quote_update.unwrap().unwrap()
-            )
-        ),
-        Err(_) => Err(http::error::MyError::ServerUnavailable),
-    }
+    .await??;
+
+    Ok(HttpResponse::Ok().json(diff))
+}
+
+#[utoipa::path(
+    path = "/api/quotes/{quote_id}/revisions/{revision}/revert",
+    responses(
+        (status = 200, description = "Quote content set back to the revision, recorded as a new revision", body = ApiQuote),
+        (status = 403, description = "Token lacks the quotes:write scope", body = ApiProblem),
+        (status = 404, description = "Quote or revision not found", body = ApiProblem)
+    ),
+    security(
+        ("token" = ["quotes:write"]),
+        ("api_key" = ["quotes:write"])
+    )
+)]
+#[post("/quotes/{quote_id}/revisions/{revision}/revert")]
+pub async fn revert(req: HttpRequest, path: Path<(String, i32)>, store: web::Data<dyn QuoteStore>) -> Result<HttpResponse, http::error::MyError> {
+    require_scope(&req, SCOPE_QUOTES_WRITE)?;
+
+    let (quote_id, revision) = path.into_inner();
+
+    let quote = web::block(move || store.revert_quote(&quote_id, revision)).await??;
+
+    Ok(HttpResponse::Ok().json(quote))
 }
 
 #[actix_web::test]
 async fn test_get_list() {
     use actix_web::test;
+    use actix_web::http::header::ContentType;
     use dotenv::dotenv;
     use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
 
     dotenv().ok();
     let pool = crate::db::pool::build_db_pool(
         std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
     );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
 
     let app = test::init_service(
         App::new()
-            .app_data(web::Data::new(pool.clone()))
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
             .service(http::controllers::quotes::list)
     ).await;
     let req = test::TestRequest::get().uri("/quotes")
@@ -231,20 +535,529 @@ async fn test_get_list() {
     assert!(success);
 }
 
+#[actix_web::test]
+async fn test_get_list_paginated() {
+    use actix_web::test;
+    use actix_web::http::header::ContentType;
+    use dotenv::dotenv;
+    use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::quotes::list)
+    ).await;
+
+    let req = test::TestRequest::get().uri("/quotes?limit=2")
+        .insert_header(ContentType::json())
+        .to_request();
+    let first_page: ApiQuotePage = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(first_page.items.len(), 2);
+    let cursor = first_page.next_cursor.expect("a second page should exist");
+    let last_id = first_page.items[1].quote.id.clone();
+    assert_eq!(QuoteCursor::decode(&cursor).map(|decoded| decoded.id), Some(last_id.clone()));
+
+    let req = test::TestRequest::get().uri(&format!("/quotes?limit=2&cursor={}", cursor))
+        .insert_header(ContentType::json())
+        .to_request();
+    let second_page: ApiQuotePage = test::call_and_read_body_json(&app, req).await;
+
+    assert!(second_page.items.iter().all(|page_item| page_item.quote.id > last_id));
+}
+
+#[actix_web::test]
+async fn test_get_list_sorted_by_update() {
+    use actix_web::test;
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::ContentType;
+    use dotenv::dotenv;
+    use actix_web::App;
+    use chrono::SecondsFormat;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::quotes::list)
+    ).await;
+
+    let req = test::TestRequest::get().uri("/quotes?limit=2&sort=updated_at&order=desc")
+        .insert_header(ContentType::json())
+        .to_request();
+    let first_page: ApiQuotePage = test::call_and_read_body_json(&app, req).await;
+
+    assert!(first_page.items[0].quote.updated_at >= first_page.items[1].quote.updated_at);
+    let last_updated_at = first_page.items[1].quote.updated_at;
+
+    let req = test::TestRequest::get().uri(&format!("/quotes?limit=2&sort=updated_at&order=desc&cursor={}", first_page.next_cursor.unwrap()))
+        .insert_header(ContentType::json())
+        .to_request();
+    let second_page: ApiQuotePage = test::call_and_read_body_json(&app, req).await;
+
+    assert!(second_page.items.iter().all(|page_item| page_item.quote.updated_at <= last_updated_at));
+
+    let since = (Utc::now() + chrono::Duration::hours(1)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let req = test::TestRequest::get().uri(&format!("/quotes?updated_since={}", since))
+        .insert_header(ContentType::json())
+        .to_request();
+    let future_page: ApiQuotePage = test::call_and_read_body_json(&app, req).await;
+
+    assert!(future_page.items.is_empty());
+
+    // A cursor of the id sort
+    let cursor = QuoteCursor { timestamp: None, id: "22e78eeb-3729-431e-aa80-60aea434088e".to_string() }.encode();
+    let req = test::TestRequest::get().uri(&format!("/quotes?sort=created_at&cursor={}", cursor))
+        .insert_header(ContentType::json())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_search() {
+    use actix_web::test;
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::ContentType;
+    use dotenv::dotenv;
+    use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
+    use uuid::Uuid;
+    use crate::db::repositories::author::AuthorRepository;
+    use crate::db::repositories::quote::QuoteRepository;
+    use crate::db::entities::quote::Quote;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
+
+    let mut conn = pool.get().unwrap();
+    let author = AuthorRepository.find_or_create("Professeur Tournesol", &mut conn).unwrap();
+    let quote = Quote {
+        id: Uuid::new_v4().to_string(),
+        author: author.name,
+        quote: "Les sous-marins requins sont parfaitement etanches".to_string(),
+        author_id: author.id,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        deleted_at: None,
+        version: 1,
+        created_by: None,
+    };
+    QuoteRepository.insert(quote.clone(), &mut conn).unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::quotes::search)
+    ).await;
+
+    let req = test::TestRequest::get().uri("/quotes/search?q=tournesol%20requins")
+        .insert_header(ContentType::json())
+        .to_request();
+    let found: Vec<Quote> = test::call_and_read_body_json(&app, req).await;
+
+    assert!(found.iter().any(|found_quote| found_quote.id == quote.id));
+
+    let req = test::TestRequest::get().uri("/quotes/search?q=%20")
+        .insert_header(ContentType::json())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_random() {
+    use actix_web::test;
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::ContentType;
+    use dotenv::dotenv;
+    use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
+    use uuid::Uuid;
+    use crate::db::repositories::author::AuthorRepository;
+    use crate::db::repositories::quote::QuoteRepository;
+    use crate::db::entities::quote::Quote;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
+
+    let mut conn = pool.get().unwrap();
+    let author = AuthorRepository.find_or_create("Capitaine Haddock", &mut conn).unwrap();
+    let quote = Quote {
+        id: Uuid::new_v4().to_string(),
+        author: author.name,
+        quote: "Mille millions de mille sabords".to_string(),
+        author_id: author.id,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        deleted_at: None,
+        version: 1,
+        created_by: None,
+    };
+    QuoteRepository.insert(quote.clone(), &mut conn).unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::quotes::random)
+    ).await;
+
+    let req = test::TestRequest::get().uri("/quotes/random")
+        .insert_header(ContentType::json())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get().uri("/quotes/random?author=capitaine%20haddock%20")
+        .insert_header(ContentType::json())
+        .to_request();
+    let picked: Quote = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(picked.author_id, quote.author_id);
+
+    let req = test::TestRequest::get().uri(&format!("/quotes/random?author={}", Uuid::new_v4()))
+        .insert_header(ContentType::json())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_daily() {
+    use actix_web::test;
+    use actix_web::http::header::ContentType;
+    use dotenv::dotenv;
+    use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
+    use crate::db::entities::quote::Quote;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::quotes::daily)
+    ).await;
+
+    let req = test::TestRequest::get().uri("/quotes/daily")
+        .insert_header(ContentType::json())
+        .to_request();
+    let first: Quote = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::get().uri("/quotes/daily")
+        .insert_header(ContentType::json())
+        .to_request();
+    let second: Quote = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(first.id, second.id);
+}
+
+#[actix_web::test]
+async fn test_pin_daily() {
+    use actix_web::test;
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::ContentType;
+    use actix_web::HttpMessage;
+    use dotenv::dotenv;
+    use actix_web::App;
+    use crate::http::auth::Claims;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::quotes::pin_daily)
+    ).await;
+    let payload = ApiPayloadDailyQuote {
+        quote_id: "072f58a7-4150-431e-3729-60aea434088e".to_string(),
+        day: chrono::NaiveDate::from_ymd_opt(2100, 1, 1),
+    };
+
+    let req = test::TestRequest::put().uri("/quotes/daily")
+        .insert_header(ContentType::json())
+        .set_json(&payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::put().uri("/quotes/daily")
+        .insert_header(ContentType::json())
+        .set_json(&payload)
+        .to_request();
+    req.extensions_mut().insert(Claims { scope: "admin".to_string(), ..Claims::default() });
+    let pinned: DailyQuote = test::call_and_read_body_json(&app, req).await;
+
+    assert!(pinned.pinned);
+    assert_eq!(pinned.quote_id, payload.quote_id);
+}
+
+#[actix_web::test]
+async fn test_delete_and_restore() {
+    use actix_web::test;
+    use actix_web::http::StatusCode;
+    use actix_web::HttpMessage;
+    use dotenv::dotenv;
+    use actix_web::App;
+    use crate::http::auth::Claims;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
+    use uuid::Uuid;
+    use crate::db::repositories::author::AuthorRepository;
+    use crate::db::repositories::quote::QuoteRepository;
+    use crate::db::entities::quote::Quote;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
+
+    let mut conn = pool.get().unwrap();
+    let author = AuthorRepository.find_or_create("Dupond et Dupont", &mut conn).unwrap();
+    let quote = QuoteRepository.insert(Quote {
+        id: Uuid::new_v4().to_string(),
+        author: author.name,
+        quote: "Je dirais meme plus".to_string(),
+        author_id: author.id,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        deleted_at: None,
+        version: 1,
+        created_by: None,
+    }, &mut conn).unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::quotes::restore)
+            .service(http::controllers::quotes::item)
+            .service(http::controllers::quotes::delete)
+    ).await;
+    let admin = || Claims { scope: "admin".to_string(), ..Claims::default() };
+
+    let req = test::TestRequest::delete().uri(&format!("/quotes/{}", quote.id)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::delete().uri(&format!("/quotes/{}", quote.id)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::get().uri(&format!("/quotes/{}", quote.id)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::get().uri(&format!("/quotes/{}?include_deleted=true", quote.id)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get().uri(&format!("/quotes/{}?include_deleted=true", quote.id)).to_request();
+    req.extensions_mut().insert(admin());
+    let deleted: Quote = test::call_and_read_body_json(&app, req).await;
+    assert!(deleted.deleted_at.is_some());
+
+    let req = test::TestRequest::post().uri(&format!("/quotes/{}/restore", quote.id)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post().uri(&format!("/quotes/{}/restore", quote.id)).to_request();
+    req.extensions_mut().insert(admin());
+    let restored: Quote = test::call_and_read_body_json(&app, req).await;
+    assert!(restored.deleted_at.is_none());
+
+    let req = test::TestRequest::get().uri(&format!("/quotes/{}", quote.id)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+}
+
+#[actix_web::test]
+async fn test_revisions() {
+    use actix_web::test;
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::ContentType;
+    use dotenv::dotenv;
+    use actix_web::App;
+    use crate::db::entities::quote_revision::{ApiRevisionDiff, QuoteRevision};
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
+    use crate::db::entities::quote::Quote;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::quotes::add)
+            .service(http::controllers::quotes::revisions)
+            .service(http::controllers::quotes::revision_diff)
+            .service(http::controllers::quotes::revert)
+            .service(http::controllers::quotes::update)
+    ).await;
+
+    let req = test::TestRequest::post().uri("/quotes")
+        .insert_header(ContentType::json())
+        .set_json(ApiPayloadQuote{quote: "Ceci n'est pas une pipe".to_string(), author: "Rene Magritte".to_string(), tags: None})
+        .to_request();
+    let created: Quote = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::put().uri(&format!("/quotes/{}", created.id))
+        .insert_header(ContentType::json())
+        .set_json(ApiPayloadQuote{quote: "Ceci n'est toujours pas une pipe".to_string(), author: "Rene Magritte".to_string(), tags: None})
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get().uri(&format!("/quotes/{}/revisions", created.id)).to_request();
+    let history: Vec<QuoteRevision> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(history.iter().map(|revision| revision.revision).collect::<Vec<i32>>(), vec![1, 2]);
+
+    let req = test::TestRequest::get().uri(&format!("/quotes/{}/revisions/diff?from=1&to=2", created.id)).to_request();
+    let diff: ApiRevisionDiff = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(diff.changes.len(), 1);
+    assert_eq!(diff.changes[0].field, "quote");
+
+    let req = test::TestRequest::post().uri(&format!("/quotes/{}/revisions/1/revert", created.id)).to_request();
+    let reverted: Quote = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(reverted.quote, created.quote);
+
+    let req = test::TestRequest::get().uri(&format!("/quotes/{}/revisions", created.id)).to_request();
+    let history: Vec<QuoteRevision> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(history.len(), 3);
+
+    let req = test::TestRequest::post().uri(&format!("/quotes/{}/revisions/42/revert", created.id)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn test_tags() {
+    use actix_web::test;
+    use actix_web::http::header::ContentType;
+    use dotenv::dotenv;
+    use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
+    use uuid::Uuid;
+    use crate::db::entities::quote::ApiQuote;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::quotes::list)
+            .service(http::controllers::quotes::add)
+    ).await;
+    let theme = format!("theme-{}", Uuid::new_v4());
+
+    let req = test::TestRequest::post().uri("/quotes")
+        .insert_header(ContentType::json())
+        .set_json(ApiPayloadQuote{quote: "Tonnerre de Brest !".to_string(), author: "Capitaine Haddock".to_string(), tags: Some(vec![theme.clone(), "Colere ".to_string()])})
+        .to_request();
+    let both: ApiQuote = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(both.tags, vec!["colere".to_string(), theme.clone()]);
+
+    let req = test::TestRequest::post().uri("/quotes")
+        .insert_header(ContentType::json())
+        .set_json(ApiPayloadQuote{quote: "Bachi-bouzouk !".to_string(), author: "Capitaine Haddock".to_string(), tags: Some(vec![theme.clone()])})
+        .to_request();
+    let only_theme: ApiQuote = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::get().uri(&format!("/quotes?tag={}&tag=colere", theme))
+        .insert_header(ContentType::json())
+        .to_request();
+    let any: ApiQuotePage = test::call_and_read_body_json(&app, req).await;
+    let any_ids: Vec<String> = any.items.into_iter().map(|page_item| page_item.quote.id).collect();
+
+    assert!(any_ids.contains(&both.quote.id));
+    assert!(any_ids.contains(&only_theme.quote.id));
+
+    let req = test::TestRequest::get().uri(&format!("/quotes?tag={}&tag=colere&tag_mode=all", theme))
+        .insert_header(ContentType::json())
+        .to_request();
+    let all: ApiQuotePage = test::call_and_read_body_json(&app, req).await;
+    let all_ids: Vec<String> = all.items.into_iter().map(|page_item| page_item.quote.id).collect();
+
+    assert_eq!(all_ids, vec![both.quote.id]);
+}
+
 #[actix_web::test]
 async fn test_get_item() {
     use actix_web::test;
+    use actix_web::http::header::ContentType;
     use dotenv::dotenv;
     use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
 
     dotenv().ok();
     let pool = crate::db::pool::build_db_pool(
         std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
     );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
 
     let app = test::init_service(
         App::new()
-            .app_data(web::Data::new(pool.clone()))
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
             .service(http::controllers::quotes::item)
     ).await;
     let req = test::TestRequest::get().uri("/quotes/072f58a7-4150-431e-3729-60aea434088e")
@@ -262,54 +1075,68 @@ async fn test_get_item() {
 #[actix_web::test]
 async fn test_post() {
     use actix_web::test;
+    use actix_web::http::header::ContentType;
     use dotenv::dotenv;
     use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::entities::quote::ApiQuote;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
 
     dotenv().ok();
 
     let pool = crate::db::pool::build_db_pool(
         std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
     );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
 
     let app = test::init_service(
         App::new()
-            .app_data(web::Data::new(pool.clone()))
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
             .service(http::controllers::quotes::add)
     ).await;
     let req = test::TestRequest::post().uri("/quotes")
         .insert_header(ContentType::json())
-        .set_json(ApiPayloadQuote{quote: "Il ne pas respirer la compote".to_string(), author: "Tintin le beau".to_string()})
+        .set_json(ApiPayloadQuote{quote: "Il ne pas respirer la compote".to_string(), author: "Tintin le beau".to_string(), tags: None})
         .to_request();
+    req.extensions_mut().insert(Claims { sub: Some("milou".to_string()), scope: "quotes:write".to_string(), ..Claims::default() });
     let resp = test::call_service(&app, req).await;
-    let success = resp.status().is_success();
+    let status = resp.status();
 
     let body = test::read_body(resp).await;
     println!("Out: {:?}", std::str::from_utf8(&body));
 
-    assert!(success);
+    assert_eq!(status, actix_web::http::StatusCode::CREATED);
+    let created: ApiQuote = serde_json::from_slice(&body).unwrap();
+    assert_eq!(created.quote.created_by.as_deref(), Some("milou"));
 }
 
 
 #[actix_web::test]
 async fn test_put() {
     use actix_web::test;
+    use actix_web::http::header::ContentType;
     use dotenv::dotenv;
     use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
 
     dotenv().ok();
 
     let pool = crate::db::pool::build_db_pool(
         std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
     );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
 
     let app = test::init_service(
         App::new()
-            .app_data(web::Data::new(pool.clone()))
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
             .service(http::controllers::quotes::update)
     ).await;
     let req = test::TestRequest::put().uri("/quotes/172f58a7-3729-431e-aa80-9189c808623c")
         .insert_header(ContentType::json())
-        .set_json(ApiPayloadQuote{quote: "Nouvelle technique : on passe pour des cons, les autres se marrent, et on frappe. C’est nouveau. ".to_string(), author: "Tintin le beau".to_string()})
+        .set_json(ApiPayloadQuote{quote: "Nouvelle technique : on passe pour des cons, les autres se marrent, et on frappe. C’est nouveau. ".to_string(), author: "Tintin le beau".to_string(), tags: None})
         .to_request();
     let resp = test::call_service(&app, req).await;
     let success = resp.status().is_success();
@@ -319,3 +1146,272 @@ async fn test_put() {
 
     assert!(success);
 }
+
+#[actix_web::test]
+async fn test_etag() {
+    use actix_web::test;
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::{ETAG, IF_MATCH, IF_NONE_MATCH};
+    use dotenv::dotenv;
+    use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
+
+    dotenv().ok();
+
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::quotes::item)
+            .service(http::controllers::quotes::add)
+            .service(http::controllers::quotes::update)
+            .service(http::controllers::quotes::delete)
+    ).await;
+
+    let req = test::TestRequest::post().uri("/quotes")
+        .set_json(ApiPayloadQuote{quote: "Deux écritures concurrentes".to_string(), author: "Etag concurrent".to_string(), tags: Some(vec!["etag".to_string()])})
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let etag = resp.headers().get(ETAG).unwrap().to_str().unwrap().to_string();
+    let created: crate::db::entities::quote::ApiQuote = test::read_body_json(resp).await;
+    assert_eq!(etag, format!("\"{}-{}\"", created.quote.id, created.quote.version));
+    let uri = format!("/quotes/{}", created.quote.id);
+
+    let req = test::TestRequest::get().uri(&uri).insert_header((IF_NONE_MATCH, etag.clone())).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+    let req = test::TestRequest::put().uri(&uri)
+        .insert_header((IF_MATCH, format!("\"{}-0\"", created.quote.id)))
+        .set_json(ApiPayloadQuote{quote: "Première écriture perdue".to_string(), author: "Etag concurrent".to_string(), tags: None})
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+    let req = test::TestRequest::put().uri(&uri)
+        .insert_header((IF_MATCH, etag.clone()))
+        .set_json(ApiPayloadQuote{quote: "Seconde écriture gagnante".to_string(), author: "Etag concurrent".to_string(), tags: Some(vec!["etag".to_string(), "gagnant".to_string()])})
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let new_etag = resp.headers().get(ETAG).unwrap().to_str().unwrap().to_string();
+    assert_ne!(new_etag, etag);
+
+    let req = test::TestRequest::get().uri(&uri).insert_header((IF_NONE_MATCH, etag.clone())).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get(ETAG).unwrap().to_str().unwrap(), new_etag);
+
+    let req = test::TestRequest::delete().uri(&uri).insert_header((IF_MATCH, etag)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+    let req = test::TestRequest::delete().uri(&uri).insert_header((IF_MATCH, new_etag)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+}
+
+#[actix_web::test]
+async fn test_patch() {
+    use actix_web::test;
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::ContentType;
+    use actix_web::http::header::CONTENT_TYPE;
+    use dotenv::dotenv;
+    use actix_web::App;
+    use std::sync::Arc;
+    use crate::db::store::{SharedQuoteStore, pg::PgQuoteStore};
+    use crate::db::entities::quote::ApiQuote;
+
+    dotenv().ok();
+
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let store: SharedQuoteStore = Arc::new(PgQuoteStore::new(pool.clone()));
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read quotes:write quotes:delete"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::quotes::add)
+            .service(http::controllers::quotes::patch)
+    ).await;
+
+    let req = test::TestRequest::post().uri("/quotes")
+        .set_json(ApiPayloadQuote{quote: "Une faute de frappe".to_string(), author: "Patch Auteur Fautif".to_string(), tags: Some(vec!["patch".to_string()])})
+        .to_request();
+    let created: ApiQuote = test::call_and_read_body_json(&app, req).await;
+    let uri = format!("/quotes/{}", created.quote.id);
+
+    let req = test::TestRequest::patch().uri(&uri)
+        .insert_header((CONTENT_TYPE, "application/merge-patch+json"))
+        .set_payload(r#"{"author": "Patch Auteur Corrigé"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let patched: ApiQuote = test::read_body_json(resp).await;
+    assert_eq!(patched.quote.author, "Patch Auteur Corrigé");
+    assert_eq!(patched.quote.quote, "Une faute de frappe");
+    assert_eq!(patched.tags, vec!["patch".to_string()]);
+
+    let req = test::TestRequest::patch().uri(&uri)
+        .insert_header((CONTENT_TYPE, "application/json-patch+json"))
+        .set_payload(r#"[{"op": "replace", "path": "/quote", "value": "Une faute corrigée"}, {"op": "add", "path": "/tags/-", "value": "corrige"}]"#)
+        .to_request();
+    let patched: ApiQuote = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(patched.quote.quote, "Une faute corrigée");
+    assert_eq!(patched.tags, vec!["corrige".to_string(), "patch".to_string()]);
+
+    let req = test::TestRequest::patch().uri(&uri)
+        .insert_header((CONTENT_TYPE, "application/merge-patch+json"))
+        .set_payload(r#"{"quote": "Nope"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let req = test::TestRequest::patch().uri(&uri)
+        .insert_header((CONTENT_TYPE, "application/json-patch+json"))
+        .set_payload(r#"[{"op": "remove", "path": "/missing"}]"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::patch().uri(&uri)
+        .insert_header(ContentType::json())
+        .set_payload(r#"{"quote": "Pas le bon format"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[actix_web::test]
+async fn test_scopes() {
+    use actix_web::test;
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use std::sync::Arc;
+    use crate::db::store::SharedQuoteStore;
+    use crate::db::store::memory::MemoryQuoteStore;
+    use actix_web::HttpMessage;
+    use crate::http::auth::Claims;
+    use crate::http::error::ApiProblem;
+
+    let store: SharedQuoteStore = Arc::new(MemoryQuoteStore::default());
+    let quote = store.create_quote(&ApiPayloadQuote {
+        author: "Capitaine Haddock".to_string(),
+        quote: "Mille millions de mille sabords".to_string(),
+        tags: None,
+    }, None).unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:read"))
+            .app_data(web::Data::from(store.clone()))
+            .service(http::controllers::quotes::item)
+            .service(http::controllers::quotes::delete)
+    ).await;
+
+    let req = test::TestRequest::get().uri(&format!("/quotes/{}", quote.quote.id)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::delete().uri(&format!("/quotes/{}", quote.quote.id)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let problem: ApiProblem = test::read_body_json(resp).await;
+    assert_eq!(problem.detail.as_deref(), Some("The token does not grant the quotes:delete scope"));
+
+    // The admin scope grants every scope
+    let req = test::TestRequest::delete().uri(&format!("/quotes/{}", quote.quote.id)).to_request();
+    req.extensions_mut().insert(Claims { scope: "admin".to_string(), ..Claims::default() });
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+}
+
+#[actix_web::test]
+async fn test_patch_after_concurrent_put() {
+    use actix_web::test;
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use actix_web::http::header::CONTENT_TYPE;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use chrono::{DateTime, NaiveDate};
+    use crate::db::entities::author::Author;
+    use crate::db::entities::quote::ApiQuote;
+    use crate::db::entities::quote_revision::QuoteRevision;
+    use crate::db::migrations::MigrationError;
+    use crate::db::store::memory::MemoryQuoteStore;
+    use crate::http::error::MyError;
+
+    /// Memory store where a PUT lands right after the first read of a quote.
+    #[derive(Default)]
+    struct RacingStore {
+        inner: MemoryQuoteStore,
+        raced: AtomicBool,
+    }
+
+    impl QuoteStore for RacingStore {
+        fn list_quotes(&self, limit: i64, cursor: Option<QuoteCursor>, sort: QuoteSort, filters: &QuoteFilters) -> Result<Vec<ApiQuote>, MyError> { self.inner.list_quotes(limit, cursor, sort, filters) }
+        fn search_quotes(&self, terms: &str, limit: i64) -> Result<Vec<ApiQuote>, MyError> { self.inner.search_quotes(terms, limit) }
+        fn random_quote(&self, filters: &QuoteFilters) -> Result<ApiQuote, MyError> { self.inner.random_quote(filters) }
+        fn daily_quote(&self, day: NaiveDate) -> Result<ApiQuote, MyError> { self.inner.daily_quote(day) }
+        fn pin_daily_quote(&self, daily_quote: DailyQuote) -> Result<DailyQuote, MyError> { self.inner.pin_daily_quote(daily_quote) }
+        fn get_quote(&self, id: &str, include_deleted: bool) -> Result<ApiQuote, MyError> {
+            let read = self.inner.get_quote(id, include_deleted)?;
+            if !self.raced.swap(true, Ordering::SeqCst) {
+                let put = ApiPayloadQuote { author: read.quote.author.clone(), quote: "Ecrit entre temps".to_string(), tags: None };
+                self.inner.update_quote(id, &put, None)?;
+            }
+
+            Ok(read)
+        }
+        fn create_quote(&self, payload: &ApiPayloadQuote, created_by: Option<&str>) -> Result<ApiQuote, MyError> { self.inner.create_quote(payload, created_by) }
+        fn update_quote(&self, id: &str, payload: &ApiPayloadQuote, expected_version: Option<i32>) -> Result<ApiQuote, MyError> { self.inner.update_quote(id, payload, expected_version) }
+        fn delete_quote(&self, id: &str, expected_version: Option<i32>) -> Result<(), MyError> { self.inner.delete_quote(id, expected_version) }
+        fn restore_quote(&self, id: &str) -> Result<ApiQuote, MyError> { self.inner.restore_quote(id) }
+        fn purge_deleted_quotes(&self, deleted_before: DateTime<Utc>) -> Result<usize, MyError> { self.inner.purge_deleted_quotes(deleted_before) }
+        fn quote_revisions(&self, id: &str) -> Result<Vec<QuoteRevision>, MyError> { self.inner.quote_revisions(id) }
+        fn quote_revision(&self, id: &str, revision: i32) -> Result<QuoteRevision, MyError> { self.inner.quote_revision(id, revision) }
+        fn revert_quote(&self, id: &str, revision: i32) -> Result<ApiQuote, MyError> { self.inner.revert_quote(id, revision) }
+        fn list_authors(&self, limit: i64, cursor: Option<String>) -> Result<Vec<Author>, MyError> { self.inner.list_authors(limit, cursor) }
+        fn get_author(&self, id: &str) -> Result<Author, MyError> { self.inner.get_author(id) }
+        fn create_author(&self, author: Author) -> Result<Author, MyError> { self.inner.create_author(author) }
+        fn update_author(&self, author: Author) -> Result<Author, MyError> { self.inner.update_author(author) }
+        fn delete_author(&self, id: &str) -> Result<(), MyError> { self.inner.delete_author(id) }
+        fn pending_migrations(&self) -> Result<Vec<String>, MigrationError> { self.inner.pending_migrations() }
+        fn run_pending_migrations(&self) -> Result<Vec<String>, MigrationError> { self.inner.run_pending_migrations() }
+    }
+
+    let store = Arc::new(RacingStore::default());
+    let created = store.create_quote(&ApiPayloadQuote {
+        author: "Seraphin Lampion".to_string(),
+        quote: "Mon cousin dans les assurances".to_string(),
+        tags: None,
+    }, None).unwrap();
+
+    let shared: Arc<dyn QuoteStore> = store.clone();
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(http::auth::grant_scopes("quotes:write"))
+            .app_data(web::Data::from(shared))
+            .service(http::controllers::quotes::patch)
+    ).await;
+
+    // No If-Match, the PUT landing after the read still fails the patch
+    let req = test::TestRequest::patch().uri(&format!("/quotes/{}", created.quote.id))
+        .insert_header((CONTENT_TYPE, "application/merge-patch+json"))
+        .set_payload(r#"{"author": "Seraphin Lampion Assurances"}"#)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::PRECONDITION_FAILED);
+
+    let current = store.get_quote(&created.quote.id, false).unwrap();
+    assert_eq!(current.quote.quote, "Ecrit entre temps");
+    assert_eq!(current.quote.author, "Seraphin Lampion");
+}
@@ -1,37 +1,395 @@
+use std::collections::BTreeMap;
+
 use actix_web::{
-    HttpResponse, error,
-    http::{header::ContentType, StatusCode},
+    HttpRequest, HttpResponse, error,
+    body::{self, MessageBody},
+    dev::ServiceResponse,
+    http::{header::{self, HeaderValue}, StatusCode},
+    middleware::ErrorHandlerResponse,
 };
 use derive_more::{Display, Error};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::ValidationErrors;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
 
 #[derive(Debug, Display, Error)]
 pub enum MyError {
     #[display(fmt = "Bad request")]
     BadClientData,
 
+    /// Request body that could not be read, with the reason
+    #[display(fmt = "Invalid payload: {}", _0)]
+    InvalidPayload(#[error(not(source))] String),
+
+    #[display(fmt = "Validation failed")]
+    Validation(#[error(not(source))] ValidationErrors),
+
     #[display(fmt = "Not found")]
     NotFount,
 
+    /// No database connection could be obtained from the pool in time
     #[display(fmt = "Server Unavailable")]
     ServerUnavailable,
 
-    #[display(fmt = "Unauthorized")]
-    Unauthorized,
+    #[display(fmt = "Internal server error")]
+    Internal,
+
+    /// Bearer token refused, with the reason
+    #[display(fmt = "Invalid token: {}", _0)]
+    InvalidToken(#[error(not(source))] String),
+
+    /// `X-Api-Key` header holding an unknown, expired or revoked key
+    #[display(fmt = "Invalid API key")]
+    InvalidApiKey,
+
+    /// Unknown user or wrong password at login
+    #[display(fmt = "Invalid user credentials")]
+    InvalidCredentials,
+
+    /// Refresh token unknown, expired, revoked or already exchanged
+    #[display(fmt = "Invalid refresh token")]
+    InvalidGrant,
+
+    /// Unknown client or wrong secret when exchanging credentials for a token
+    #[display(fmt = "Invalid client credentials")]
+    InvalidClient,
+
+    /// The token does not grant the scope the operation requires
+    #[display(fmt = "Missing scope: {}", _0)]
+    MissingScope(#[error(not(source))] String),
+
+    /// Unique constraint violated, with the constraint name
+    #[display(fmt = "Unique violation: {}", _0)]
+    UniqueViolation(#[error(not(source))] String),
+
+    /// Foreign key constraint violated, with the constraint name
+    #[display(fmt = "Foreign key violation: {}", _0)]
+    ForeignKeyViolation(#[error(not(source))] String),
+
+    #[display(fmt = "Precondition failed")]
+    PreconditionFailed,
+
+    #[display(fmt = "Unsupported media type")]
+    UnsupportedMediaType,
+
+    /// Feature the configured store does not back, with what to do instead
+    #[display(fmt = "Not implemented: {}", _0)]
+    NotImplemented(#[error(not(source))] String),
+}
+
+/// Seconds a client is told to wait before retrying when the database is unavailable.
+const RETRY_AFTER_SECONDS: &str = "1";
+
+impl From<ValidationErrors> for MyError {
+    fn from(errors: ValidationErrors) -> Self {
+        MyError::Validation(errors)
+    }
+}
+
+impl From<DieselError> for MyError {
+    fn from(err: DieselError) -> Self {
+        match err {
+            DieselError::NotFound => {
+                debug!("Record not found");
+                MyError::NotFount
+            },
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                warn!("Unique violation on table {:?}: {}", info.table_name(), info.message());
+                MyError::UniqueViolation(info.constraint_name().unwrap_or_default().to_string())
+            },
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) => {
+                warn!("Foreign key violation on table {:?}: {}", info.table_name(), info.message());
+                MyError::ForeignKeyViolation(info.constraint_name().unwrap_or_default().to_string())
+            },
+            err => {
+                error!("Database error: {:?}", err);
+                MyError::Internal
+            },
+        }
+    }
+}
+
+impl From<r2d2::Error> for MyError {
+    fn from(err: r2d2::Error) -> Self {
+        warn!("No database connection available: {}", err);
+        MyError::ServerUnavailable
+    }
+}
+
+impl From<error::BlockingError> for MyError {
+    fn from(err: error::BlockingError) -> Self {
+        error!("Blocking task failed: {}", err);
+        MyError::Internal
+    }
+}
+
+/// Error body of the API, following RFC 7807 (`application/problem+json`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiProblem {
+    /// URI identifying the kind of problem, `about:blank` when the status says it all
+    #[serde(rename = "type", default = "about_blank")]
+    pub problem_type: String,
+    /// Short summary of the kind of problem
+    pub title: String,
+    pub status: u16,
+    /// Explanation specific to this occurrence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Path of the request the problem occurred on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// Messages of the invalid fields, by field name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<BTreeMap<String, Vec<String>>>,
+}
+
+fn about_blank() -> String {
+    "about:blank".to_string()
+}
+
+impl ApiProblem {
+    pub fn new(status: StatusCode) -> ApiProblem {
+        ApiProblem {
+            problem_type: about_blank(),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: None,
+            instance: None,
+            errors: None,
+        }
+    }
+
+    pub fn response(&self) -> HttpResponse {
+        HttpResponse::build(StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
+            .insert_header((header::CONTENT_TYPE, PROBLEM_JSON))
+            .json(self)
+    }
+}
+
+impl MyError {
+    fn problem(&self) -> ApiProblem {
+        let mut problem = ApiProblem::new(error::ResponseError::status_code(self));
+
+        match self {
+            MyError::InvalidPayload(reason) => problem.detail = Some(reason.clone()),
+            MyError::Validation(errors) => {
+                problem.detail = Some("The payload has invalid fields".to_string());
+                problem.errors = Some(field_errors(errors));
+            },
+            MyError::PreconditionFailed => problem.detail = Some("The resource changed since the given ETag".to_string()),
+            MyError::UniqueViolation(constraint) => problem.detail = Some(format!("Conflicts with an existing resource ({})", constraint)),
+            MyError::ForeignKeyViolation(constraint) => problem.detail = Some(format!("References a missing resource, or is still referenced ({})", constraint)),
+            MyError::InvalidToken(reason) => problem.detail = Some(reason.clone()),
+            MyError::MissingScope(scope) => problem.detail = Some(format!("The token does not grant the {} scope", scope)),
+            MyError::InvalidApiKey => problem.detail = Some("Unknown, expired or revoked API key".to_string()),
+            MyError::InvalidClient => problem.detail = Some("Unknown client or wrong secret".to_string()),
+            MyError::InvalidCredentials => problem.detail = Some("Unknown user or wrong password".to_string()),
+            MyError::InvalidGrant => problem.detail = Some("Unknown, expired or revoked refresh token, log in again".to_string()),
+            MyError::ServerUnavailable => problem.detail = Some("The database is unavailable, retry later".to_string()),
+            MyError::NotImplemented(reason) => problem.detail = Some(reason.clone()),
+            _ => {},
+        }
+
+        problem
+    }
+}
+
+fn field_errors(errors: &ValidationErrors) -> BTreeMap<String, Vec<String>> {
+    errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, field_errors)| {
+            let messages = field_errors
+                .iter()
+                .map(|field_error| match &field_error.message {
+                    Some(message) => message.to_string(),
+                    None => field_error.code.to_string(),
+                })
+                .collect();
+
+            (field.to_string(), messages)
+        })
+        .collect()
 }
 
 impl error::ResponseError for MyError {
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code())
-            .insert_header(ContentType::html())
-            .body(self.to_string())
+        let mut response = self.problem().response();
+
+        match self {
+            MyError::ServerUnavailable => {
+                response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from_static(RETRY_AFTER_SECONDS));
+            },
+            // RFC 6750 challenge, so that clients know whether to fetch a new token
+            MyError::InvalidToken(reason) => {
+                let challenge = format!("Bearer error=\"invalid_token\", error_description=\"{}\"", reason.replace('"', "'"));
+                if let Ok(challenge) = HeaderValue::from_str(&challenge) {
+                    response.headers_mut().insert(header::WWW_AUTHENTICATE, challenge);
+                }
+            },
+            MyError::MissingScope(scope) => {
+                let challenge = format!("Bearer error=\"insufficient_scope\", scope=\"{}\"", scope);
+                if let Ok(challenge) = HeaderValue::from_str(&challenge) {
+                    response.headers_mut().insert(header::WWW_AUTHENTICATE, challenge);
+                }
+            },
+            _ => {},
+        }
+
+        response
     }
 
     fn status_code(&self) -> StatusCode {
         match *self {
             MyError::BadClientData => StatusCode::BAD_REQUEST,
+            MyError::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+            MyError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
             MyError::NotFount => StatusCode::NOT_FOUND,
-            MyError::Unauthorized => StatusCode::UNAUTHORIZED,
+            MyError::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            MyError::InvalidApiKey => StatusCode::UNAUTHORIZED,
+            MyError::InvalidClient => StatusCode::UNAUTHORIZED,
+            MyError::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            MyError::InvalidGrant => StatusCode::UNAUTHORIZED,
+            MyError::MissingScope(_) => StatusCode::FORBIDDEN,
+            MyError::UniqueViolation(_) => StatusCode::CONFLICT,
+            MyError::ForeignKeyViolation(_) => StatusCode::CONFLICT,
+            MyError::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            MyError::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
             MyError::ServerUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            MyError::NotImplemented(_) => StatusCode::NOT_IMPLEMENTED,
+            MyError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
+
+/// Error handler of `web::JsonConfig`, malformed bodies are reported as problems too.
+pub fn json_error_handler(err: error::JsonPayloadError, _req: &HttpRequest) -> error::Error {
+    MyError::InvalidPayload(err.to_string()).into()
+}
+
+/// Error handler of `web::QueryConfig`.
+pub fn query_error_handler(err: error::QueryPayloadError, _req: &HttpRequest) -> error::Error {
+    MyError::InvalidPayload(err.to_string()).into()
+}
+
+/// `ErrorHandlers` default handler, turns every error response into a problem and sets its
+/// `instance` to the request path.
+///
+/// Responses which are not problems yet (unknown routes, authentication failures...) get one
+/// built from their status, their text body becoming the detail.
+pub fn problem_details<B: MessageBody + 'static>(res: ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let is_problem = res.headers().get(header::CONTENT_TYPE) == Some(&HeaderValue::from_static(PROBLEM_JSON));
+
+    Ok(ErrorHandlerResponse::Future(Box::pin(async move {
+        let (req, res) = res.into_parts();
+        let status = res.status();
+        let headers = res.headers().clone();
+        let content = body::to_bytes(res.into_body()).await.unwrap_or_default();
+
+        let mut problem = match is_problem {
+            true => serde_json::from_slice(&content).unwrap_or_else(|_| ApiProblem::new(status)),
+            false => {
+                let mut problem = ApiProblem::new(status);
+                let detail = String::from_utf8_lossy(&content).trim().to_string();
+                problem.detail = Some(detail).filter(|detail| !detail.is_empty());
+                problem
+            },
+        };
+        problem.instance = problem.instance.or_else(|| Some(req.path().to_string()));
+
+        let mut problem_response = problem.response();
+        for (name, value) in headers.iter().filter(|(name, _)| *name != header::CONTENT_TYPE && *name != header::CONTENT_LENGTH) {
+            problem_response.headers_mut().append(name.clone(), value.clone());
+        }
+
+        Ok(ServiceResponse::new(req, problem_response).map_into_right_body())
+    })))
+}
+
+#[actix_web::test]
+async fn test_problem_details() {
+    use actix_web::{test, web, App};
+    use actix_web::middleware::ErrorHandlers;
+
+    let app = test::init_service(
+        App::new()
+            .wrap(ErrorHandlers::new().default_handler(problem_details))
+            .app_data(web::JsonConfig::default().error_handler(json_error_handler))
+            .route("/missing", web::get().to(|| async { Err::<HttpResponse, _>(MyError::NotFount) }))
+            .route("/echo", web::post().to(|body: web::Json<BTreeMap<String, String>>| async move { HttpResponse::Ok().json(body.into_inner()) }))
+            .route("/teapot", web::get().to(|| async { HttpResponse::ImATeapot().body("short and stout") }))
+    ).await;
+
+    let req = test::TestRequest::get().uri("/missing").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), PROBLEM_JSON);
+    let problem: ApiProblem = test::read_body_json(resp).await;
+    assert_eq!(problem.title, "Not Found");
+    assert_eq!(problem.instance.as_deref(), Some("/missing"));
+
+    let req = test::TestRequest::post().uri("/echo").insert_header(header::ContentType::json()).set_payload("{").to_request();
+    let problem: ApiProblem = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(problem.status, 400);
+    assert!(problem.detail.is_some());
+
+    let req = test::TestRequest::get().uri("/teapot").to_request();
+    let problem: ApiProblem = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(problem.status, 418);
+    assert_eq!(problem.detail.as_deref(), Some("short and stout"));
+
+    let req = test::TestRequest::get().uri("/unknown").to_request();
+    let problem: ApiProblem = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(problem.status, 404);
+    assert_eq!(problem.instance.as_deref(), Some("/unknown"));
+}
+
+#[test]
+fn test_from_diesel_error() {
+    use actix_web::ResponseError;
+
+    let not_found = MyError::from(DieselError::NotFound);
+    assert_eq!(not_found.status_code(), StatusCode::NOT_FOUND);
+
+    let unique = MyError::from(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, Box::new("duplicate key".to_string())));
+    assert!(matches!(unique, MyError::UniqueViolation(_)));
+    assert_eq!(unique.status_code(), StatusCode::CONFLICT);
+
+    let foreign_key = MyError::from(DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, Box::new("still referenced".to_string())));
+    assert!(matches!(foreign_key, MyError::ForeignKeyViolation(_)));
+
+    let internal = MyError::from(DieselError::RollbackTransaction);
+    assert_eq!(internal.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let unavailable = MyError::ServerUnavailable.error_response();
+    assert_eq!(unavailable.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(unavailable.headers().get(header::RETRY_AFTER).unwrap(), RETRY_AFTER_SECONDS);
+}
+
+#[test]
+fn test_invalid_token_challenge() {
+    use actix_web::ResponseError;
+
+    let invalid_token = MyError::InvalidToken("The token expired".to_string()).error_response();
+    assert_eq!(invalid_token.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        invalid_token.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+        r#"Bearer error="invalid_token", error_description="The token expired""#
+    );
+}
+
+#[test]
+fn test_missing_scope_challenge() {
+    use actix_web::ResponseError;
+
+    let missing_scope = MyError::MissingScope("quotes:write".to_string()).error_response();
+    assert_eq!(missing_scope.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        missing_scope.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+        r#"Bearer error="insufficient_scope", scope="quotes:write""#
+    );
+}
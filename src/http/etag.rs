@@ -0,0 +1,39 @@
+use actix_web::http::header::{EntityTag, Header, IfMatch, IfNoneMatch, IF_MATCH};
+use actix_web::HttpRequest;
+use crate::db::entities::quote::Quote;
+use crate::http::error::MyError;
+
+/// Strong ETag of the quote, changes with its version.
+pub fn quote_etag(quote: &Quote) -> EntityTag {
+    EntityTag::new_strong(format!("{}-{}", quote.id, quote.version))
+}
+
+/// `If-Match` header of the request, `None` when the request is not conditional.
+pub fn if_match(req: &HttpRequest) -> Result<Option<IfMatch>, MyError> {
+    if !req.headers().contains_key(IF_MATCH) {
+        return Ok(None);
+    }
+
+    IfMatch::parse(req)
+        .map(Some)
+        .map_err(|_| MyError::BadClientData)
+}
+
+/// Version the quote must still have when written, `None` when the request is not conditional.
+pub fn expected_version(if_match: &Option<IfMatch>, current: &Quote) -> Result<Option<i32>, MyError> {
+    match if_match {
+        None => Ok(None),
+        Some(IfMatch::Any) => Ok(Some(current.version)),
+        Some(IfMatch::Items(etags)) if etags.iter().any(|etag| etag.strong_eq(&quote_etag(current))) => Ok(Some(current.version)),
+        Some(_) => Err(MyError::PreconditionFailed),
+    }
+}
+
+/// Whether the client copy, given through `If-None-Match`, is still current.
+pub fn is_not_modified(req: &HttpRequest, current: &Quote) -> bool {
+    match IfNoneMatch::parse(req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(etags)) => etags.iter().any(|etag| etag.weak_eq(&quote_etag(current))),
+        Err(_) => false,
+    }
+}
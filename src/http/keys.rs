@@ -0,0 +1,404 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ed25519_dalek::pkcs8::DecodePrivateKey;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+    PublicKeyUse,
+};
+use serde::{Deserialize, Serialize};
+use crate::config::env::Config;
+
+pub type KeyError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Verifies the tokens whose header carries its `kid` and `alg`.
+#[derive(Debug, Clone)]
+pub struct VerificationKey {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    pub key: DecodingKey,
+}
+
+impl VerificationKey {
+    /// HS256 shared secret, `JWT_SECRET` has no `kid`.
+    pub fn from_secret(kid: Option<&str>, secret: &str) -> Self {
+        VerificationKey {
+            kid: kid.map(str::to_string),
+            algorithm: Algorithm::HS256,
+            key: DecodingKey::from_secret(secret.as_bytes()),
+        }
+    }
+
+    /// Public key PEM, RS256 for RSA, ES256 for P-256 and EdDSA for Ed25519.
+    pub fn from_pem(kid: &str, pem: &[u8]) -> Result<Self, KeyError> {
+        let (algorithm, key) = if let Ok(key) = DecodingKey::from_rsa_pem(pem) {
+            (Algorithm::RS256, key)
+        } else if let Ok(key) = DecodingKey::from_ec_pem(pem) {
+            (Algorithm::ES256, key)
+        } else if let Ok(key) = DecodingKey::from_ed_pem(pem) {
+            (Algorithm::EdDSA, key)
+        } else {
+            return Err(format!("Key {} is not a RSA, P-256 or Ed25519 public key PEM", kid).into());
+        };
+
+        Ok(VerificationKey { kid: Some(kid.to_string()), algorithm, key })
+    }
+
+    /// Asymmetric JWK, its algorithm is the `alg` member or follows from the key type.
+    pub fn from_jwk(jwk: &Jwk) -> Result<Self, KeyError> {
+        let kid = jwk.common.key_id.clone().ok_or("Every JWKS key needs a kid")?;
+
+        let algorithm = match (&jwk.common.key_algorithm, &jwk.algorithm) {
+            (_, AlgorithmParameters::OctetKey(_)) => return Err(format!("Key {} is a shared secret, JWKS keys must be public keys", kid).into()),
+            (Some(key_algorithm), _) => key_algorithm.to_string().parse::<Algorithm>()?,
+            (None, AlgorithmParameters::RSA(_)) => Algorithm::RS256,
+            (None, AlgorithmParameters::EllipticCurve(params)) if params.curve == EllipticCurve::P256 => Algorithm::ES256,
+            (None, AlgorithmParameters::OctetKeyPair(params)) if params.curve == EllipticCurve::Ed25519 => Algorithm::EdDSA,
+            (None, _) => return Err(format!("Key {} has no alg and an unsupported curve", kid).into()),
+        };
+
+        Ok(VerificationKey { kid: Some(kid), algorithm, key: DecodingKey::from_jwk(jwk)? })
+    }
+}
+
+/// Signs the tokens the service issues.
+#[derive(Debug, Clone)]
+pub struct SigningKey {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    key: EncodingKey,
+    /// Public half, published in the JWKS, none for the shared secret
+    public_jwk: Option<Jwk>,
+    verification_key: VerificationKey,
+}
+
+impl SigningKey {
+    /// HS256 shared secret, `JWT_SECRET` has no `kid`.
+    pub fn from_secret(kid: Option<&str>, secret: &str) -> Self {
+        SigningKey {
+            kid: kid.map(str::to_string),
+            algorithm: Algorithm::HS256,
+            key: EncodingKey::from_secret(secret.as_bytes()),
+            public_jwk: None,
+            verification_key: VerificationKey::from_secret(kid, secret),
+        }
+    }
+
+    /// PKCS#8 private key PEM, RS256 for RSA, ES256 for P-256 and EdDSA for Ed25519.
+    pub fn from_pem(kid: &str, pem: &[u8]) -> Result<Self, KeyError> {
+        let (algorithm, key, mut public_jwk) = if let Ok(key) = EncodingKey::from_rsa_pem(pem) {
+            let public_jwk = Jwk::from_encoding_key(&key, Algorithm::RS256)?;
+            (Algorithm::RS256, key, public_jwk)
+        } else if let Ok(key) = EncodingKey::from_ec_pem(pem) {
+            let public_jwk = Jwk::from_encoding_key(&key, Algorithm::ES256)?;
+            (Algorithm::ES256, key, public_jwk)
+        } else if let Ok(key) = EncodingKey::from_ed_pem(pem) {
+            // jsonwebtoken does not derive Ed25519 public keys
+            let private_key = ed25519_dalek::SigningKey::from_pkcs8_pem(std::str::from_utf8(pem)?)?;
+            let mut public_jwk = Jwk {
+                common: Default::default(),
+                algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(private_key.verifying_key().to_bytes()),
+                }),
+            };
+            public_jwk.common.key_algorithm = Some(KeyAlgorithm::EdDSA);
+            (Algorithm::EdDSA, key, public_jwk)
+        } else {
+            return Err(format!("Key {} is not a RSA, P-256 or Ed25519 PKCS#8 private key PEM", kid).into());
+        };
+
+        public_jwk.common.key_id = Some(kid.to_string());
+        public_jwk.common.public_key_use = Some(PublicKeyUse::Signature);
+
+        Ok(SigningKey {
+            kid: Some(kid.to_string()),
+            algorithm,
+            key,
+            verification_key: VerificationKey::from_jwk(&public_jwk)?,
+            public_jwk: Some(public_jwk),
+        })
+    }
+
+    pub fn sign<T: Serialize>(&self, claims: &T) -> String {
+        let header = Header {
+            kid: self.kid.clone(),
+            ..Header::new(self.algorithm)
+        };
+
+        jsonwebtoken::encode(&header, claims, &self.key).expect("claims should serialize and the key sign")
+    }
+
+    pub fn public_jwk(&self) -> Option<&Jwk> {
+        self.public_jwk.as_ref()
+    }
+
+    /// Verifies what this key signs.
+    pub fn verification_key(&self) -> &VerificationKey {
+        &self.verification_key
+    }
+}
+
+/// Where the keys come from, read again on every reload.
+#[derive(Debug, Clone, Default)]
+pub struct KeySources {
+    pub secret: String,
+    pub signing_key_file: String,
+    pub signing_key_id: String,
+    pub public_key_files: String,
+    pub jwks_file: String,
+    pub keyring_file: String,
+}
+
+impl KeySources {
+    pub fn from_config(config: &Config) -> Self {
+        KeySources {
+            secret: config.jwt_secret.clone(),
+            signing_key_file: config.jwt_signing_key_file.clone(),
+            signing_key_id: config.jwt_signing_key_id.clone(),
+            public_key_files: config.jwt_public_key_files.clone(),
+            jwks_file: config.jwt_jwks_file.clone(),
+            keyring_file: config.jwt_keyring_file.clone(),
+        }
+    }
+}
+
+/// Keys in use at a time, swapped as a whole when reloaded.
+#[derive(Debug, Clone)]
+pub struct Keyring {
+    pub signing_key: SigningKey,
+    /// Picked by the `kid` and `alg` of the token header
+    pub verification_keys: Vec<VerificationKey>,
+    /// Public keys of every private key, so that tokens signed before a rotation keep verifying
+    pub published_keys: Vec<Jwk>,
+}
+
+/// JSON keyring, one signing key among keys identified by `kid`.
+#[derive(Debug, Deserialize)]
+struct KeyringFile {
+    signing_kid: String,
+    keys: Vec<KeyringEntry>,
+}
+
+/// Key of the keyring file, a HS256 secret, a private key or a public key only verifying tokens.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyringEntry {
+    kid: String,
+    secret: Option<String>,
+    private_key_file: Option<String>,
+    public_key_file: Option<String>,
+}
+
+impl Keyring {
+    pub fn new(signing_key: SigningKey) -> Self {
+        Keyring {
+            verification_keys: vec![signing_key.verification_key().clone()],
+            published_keys: signing_key.public_jwk().cloned().into_iter().collect(),
+            signing_key,
+        }
+    }
+
+    /// Signs with the keyring file or the private key file when set, with `JWT_SECRET` otherwise.
+    /// `JWT_SECRET`, the public key files and the JWKS file verify tokens as well.
+    pub fn load(sources: &KeySources) -> Result<Self, KeyError> {
+        let mut keyring = match (sources.keyring_file.is_empty(), sources.signing_key_file.is_empty(), sources.secret.is_empty()) {
+            (false, false, _) => return Err("Set JWT_KEYRING_FILE or JWT_SIGNING_KEY_FILE, not both".into()),
+            (false, true, _) => Keyring::load_file(Path::new(&sources.keyring_file))?,
+            (true, false, _) if sources.signing_key_id.is_empty() => return Err("JWT_SIGNING_KEY_ID must be set along JWT_SIGNING_KEY_FILE".into()),
+            (true, false, _) => Keyring::new(SigningKey::from_pem(&sources.signing_key_id, &read(Path::new(&sources.signing_key_file))?)?),
+            (true, true, false) => Keyring::new(SigningKey::from_secret(None, &sources.secret)),
+            (true, true, true) => return Err("JWT_SECRET, JWT_SIGNING_KEY_FILE or JWT_KEYRING_FILE must be set".into()),
+        };
+
+        // Tokens signed with JWT_SECRET have no kid
+        if keyring.signing_key.kid.is_some() && !sources.secret.is_empty() {
+            keyring.verification_keys.push(VerificationKey::from_secret(None, &sources.secret));
+        }
+        keyring.verification_keys.extend(load_public_keys(&sources.public_key_files)?);
+        if !sources.jwks_file.is_empty() {
+            keyring.verification_keys.extend(load_jwks(&sources.jwks_file)?);
+        }
+
+        Ok(keyring)
+    }
+
+    /// Key files are relative to the keyring file.
+    fn load_file(path: &Path) -> Result<Self, KeyError> {
+        let file: KeyringFile = serde_json::from_slice(&read(path)?)
+            .map_err(|err| format!("Invalid keyring {}: {}", path.display(), err))?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+
+        let mut kids = HashSet::new();
+        let mut signing_key = None;
+        let mut verification_keys = Vec::new();
+        let mut published_keys = Vec::new();
+
+        for entry in file.keys {
+            if !kids.insert(entry.kid.clone()) {
+                return Err(format!("Key {} appears twice in the keyring", entry.kid).into());
+            }
+
+            let private_key = match (entry.secret, entry.private_key_file, entry.public_key_file) {
+                (Some(secret), None, None) => SigningKey::from_secret(Some(&entry.kid), &secret),
+                (None, Some(private_key_file), None) => SigningKey::from_pem(&entry.kid, &read(&directory.join(private_key_file))?)?,
+                (None, None, Some(public_key_file)) => {
+                    verification_keys.push(VerificationKey::from_pem(&entry.kid, &read(&directory.join(public_key_file))?)?);
+                    continue;
+                },
+                _ => return Err(format!("Key {} needs one of secret, private_key_file or public_key_file", entry.kid).into()),
+            };
+
+            verification_keys.push(private_key.verification_key().clone());
+            published_keys.extend(private_key.public_jwk().cloned());
+            if entry.kid == file.signing_kid {
+                signing_key = Some(private_key);
+            }
+        }
+
+        let signing_key = signing_key
+            .ok_or_else(|| format!("The signing key {} is not a secret or private key of the keyring", file.signing_kid))?;
+
+        Ok(Keyring { signing_key, verification_keys, published_keys })
+    }
+
+    pub fn find(&self, kid: Option<&str>, algorithm: Algorithm) -> Option<&VerificationKey> {
+        self.verification_keys
+            .iter()
+            .find(|key| key.kid.as_deref() == kid && key.algorithm == algorithm)
+    }
+
+    /// Kids of the verification keys, the shared secret shows as `-`.
+    pub fn kids(&self) -> Vec<&str> {
+        self.verification_keys.iter().map(|key| key.kid.as_deref().unwrap_or("-")).collect()
+    }
+}
+
+pub fn read(path: &Path) -> Result<Vec<u8>, KeyError> {
+    fs::read(path).map_err(|err| format!("Cannot read {}: {}", path.display(), err).into())
+}
+
+/// Public keys of comma separated `kid=path/to/key.pem` entries.
+pub fn load_public_keys(entries: &str) -> Result<Vec<VerificationKey>, KeyError> {
+    entries
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (kid, path) = entry.split_once('=').ok_or_else(|| format!("Expected kid=path, got {}", entry))?;
+
+            VerificationKey::from_pem(kid, &read(Path::new(path))?)
+        })
+        .collect()
+}
+
+/// Public keys of a JWKS JSON file.
+pub fn load_jwks(path: &str) -> Result<Vec<VerificationKey>, KeyError> {
+    jwks_keys(&serde_json::from_slice(&read(Path::new(path))?)?)
+}
+
+/// Signature keys of the JWKS, identity providers publish their encryption keys along.
+pub fn jwks_keys(jwks: &JwkSet) -> Result<Vec<VerificationKey>, KeyError> {
+    jwks.keys
+        .iter()
+        .filter(|jwk| jwk.common.public_key_use != Some(PublicKeyUse::Encryption))
+        .map(VerificationKey::from_jwk)
+        .collect()
+}
+
+#[cfg(test)]
+pub const TEST_KEYS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/keys");
+
+#[test]
+fn test_pem_keys() {
+    use jsonwebtoken::Validation;
+    use std::collections::HashMap;
+
+    for (name, algorithm) in [("rsa", Algorithm::RS256), ("ec", Algorithm::ES256), ("ed25519", Algorithm::EdDSA)] {
+        let signing_key = SigningKey::from_pem(name, &fs::read(format!("{}/{}.pem", TEST_KEYS, name)).unwrap()).unwrap();
+        assert_eq!(signing_key.algorithm, algorithm);
+
+        let token = signing_key.sign(&HashMap::from([("sub", "batch-import")]));
+        assert_eq!(jsonwebtoken::decode_header(&token).unwrap().kid.as_deref(), Some(name));
+
+        let mut validation = Validation::new(algorithm);
+        validation.required_spec_claims.clear();
+
+        // The public PEM and the published JWK both verify the token
+        let public_key = load_public_keys(&format!("{}={}/{}.pub.pem", name, TEST_KEYS, name)).unwrap().remove(0);
+        let published_key = signing_key.verification_key().clone();
+        for verification_key in [public_key, published_key] {
+            assert_eq!(verification_key.algorithm, algorithm);
+            assert!(jsonwebtoken::decode::<HashMap<String, String>>(&token, &verification_key.key, &validation).is_ok());
+        }
+    }
+
+    assert!(SigningKey::from_pem("public", &fs::read(format!("{}/rsa.pub.pem", TEST_KEYS)).unwrap()).is_err());
+    assert!(load_public_keys("missing-path").is_err());
+}
+
+#[test]
+fn test_load_jwks() {
+    use uuid::Uuid;
+
+    let keys: Vec<Jwk> = ["rsa", "ec", "ed25519"]
+        .iter()
+        .map(|name| {
+            let signing_key = SigningKey::from_pem(name, &fs::read(format!("{}/{}.pem", TEST_KEYS, name)).unwrap()).unwrap();
+            signing_key.public_jwk().unwrap().clone()
+        })
+        .collect();
+
+    let path = std::env::temp_dir().join(format!("jwks-{}.json", Uuid::new_v4()));
+    fs::write(&path, serde_json::to_vec(&JwkSet { keys }).unwrap()).unwrap();
+
+    let loaded = load_jwks(&path.to_string_lossy()).unwrap();
+    let loaded: Vec<_> = loaded.iter().map(|key| (key.kid.as_deref().unwrap(), key.algorithm)).collect();
+    assert_eq!(loaded, vec![("rsa", Algorithm::RS256), ("ec", Algorithm::ES256), ("ed25519", Algorithm::EdDSA)]);
+
+    fs::write(&path, r#"{"keys": [{"kty": "oct", "kid": "secret", "k": "c2VjcmV0"}]}"#).unwrap();
+    assert!(load_jwks(&path.to_string_lossy()).is_err());
+
+    fs::remove_file(path).ok();
+}
+
+#[test]
+fn test_load_keyring() {
+    use uuid::Uuid;
+
+    let path = std::env::temp_dir().join(format!("keyring-{}.json", Uuid::new_v4()));
+    let sources = KeySources {
+        secret: "NOT_A_SECRET".to_string(),
+        keyring_file: path.to_string_lossy().to_string(),
+        ..KeySources::default()
+    };
+
+    fs::write(&path, format!(r#"{{
+        "signing_kid": "2026-q4",
+        "keys": [
+            {{"kid": "2026-q4", "private_key_file": "{keys}/ed25519.pem"}},
+            {{"kid": "2026-q3", "secret": "PREVIOUS_SECRET"}},
+            {{"kid": "partner", "public_key_file": "{keys}/rsa.pub.pem"}}
+        ]
+    }}"#, keys = TEST_KEYS)).unwrap();
+
+    let keyring = Keyring::load(&sources).unwrap();
+    assert_eq!(keyring.signing_key.kid.as_deref(), Some("2026-q4"));
+    assert_eq!(keyring.kids(), vec!["2026-q4", "2026-q3", "partner", "-"]);
+    assert!(keyring.find(Some("2026-q3"), Algorithm::HS256).is_some());
+    assert!(keyring.find(Some("2026-q3"), Algorithm::RS256).is_none());
+    // Secrets are never published
+    assert_eq!(keyring.published_keys.len(), 1);
+
+    fs::write(&path, r#"{"signing_kid": "partner", "keys": [{"kid": "partner", "public_key_file": "rsa.pub.pem"}]}"#).unwrap();
+    assert!(Keyring::load(&sources).is_err());
+
+    fs::write(&path, r#"{"signing_kid": "a", "keys": [{"kid": "a", "secret": "A"}, {"kid": "a", "secret": "B"}]}"#).unwrap();
+    assert!(Keyring::load(&sources).is_err());
+
+    fs::remove_file(path).ok();
+}
@@ -1,2 +1,9 @@
 pub mod error;
+pub mod auth;
+pub mod keys;
+pub mod oidc;
+pub mod revocation;
+pub mod sessions;
+pub mod etag;
+pub mod patch;
 pub mod controllers;
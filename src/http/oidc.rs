@@ -0,0 +1,285 @@
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use jsonwebtoken::jwk::JwkSet;
+use prometheus::IntCounterVec;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use crate::config::env::Config;
+use crate::http::auth::{check_claims, decode_signed, Claims, TokenError, UNKNOWN_KID};
+use crate::http::keys::{jwks_keys, read, KeyError, VerificationKey};
+
+/// Discovery documents and JWKS are small, anything bigger is refused.
+const MAX_DOCUMENT_BYTES: u64 = 1024 * 1024;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// OpenID Connect discovery document, only what verifying the access tokens needs.
+#[derive(Debug, Deserialize)]
+pub struct DiscoveryDocument {
+    pub issuer: String,
+    pub jwks_uri: String,
+}
+
+/// Only the issuer of a token, read before verifying it to pick the keys.
+#[derive(Debug, Deserialize)]
+struct UnverifiedIssuer {
+    iss: Option<String>,
+}
+
+/// External OpenID Connect issuer, e.g. Keycloak, whose access tokens the validator accepts along
+/// the issued ones. Clones share the cached keys.
+#[derive(Debug, Clone)]
+pub struct OidcProvider {
+    /// `iss` of the tokens, the discovery document must announce the same
+    pub issuer: String,
+    /// URL or file of the discovery document
+    pub discovery_url: String,
+    /// Required `aud`, not checked when empty
+    pub audience: String,
+    /// Dotted path of the claim granting the scopes, a space separated string or an array of strings
+    pub scope_claim: String,
+    /// Clock skew tolerated on `exp` and `nbf`
+    pub leeway_seconds: i64,
+    keys: Arc<RwLock<Arc<Vec<VerificationKey>>>>,
+    /// Shared with `JwtSettings`, by `kid` and outcome
+    verifications: IntCounterVec,
+}
+
+impl OidcProvider {
+    /// No key until `refresh` reads them.
+    pub fn new(issuer: &str, discovery_url: &str, audience: &str, scope_claim: &str, verifications: IntCounterVec) -> Self {
+        let discovery_url = match discovery_url.is_empty() {
+            true => format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/')),
+            false => discovery_url.to_string(),
+        };
+
+        OidcProvider {
+            issuer: issuer.to_string(),
+            discovery_url,
+            audience: audience.to_string(),
+            scope_claim: scope_claim.to_string(),
+            leeway_seconds: 60,
+            keys: Arc::new(RwLock::new(Arc::new(Vec::new()))),
+            verifications,
+        }
+    }
+
+    /// None when `OIDC_ISSUER` is empty, otherwise loads the keys so that a wrong setup fails at boot.
+    pub fn from_config(config: &Config, verifications: IntCounterVec) -> Result<Option<Self>, KeyError> {
+        if config.oidc_issuer.is_empty() {
+            return Ok(None);
+        }
+
+        let provider = OidcProvider {
+            leeway_seconds: config.jwt_leeway as i64,
+            ..OidcProvider::new(&config.oidc_issuer, &config.oidc_discovery_url, &config.oidc_audience, &config.oidc_scope_claim, verifications)
+        };
+        provider.refresh()?;
+
+        Ok(Some(provider))
+    }
+
+    pub fn keys(&self) -> Arc<Vec<VerificationKey>> {
+        self.keys.read().expect("OIDC keys lock poisoned").clone()
+    }
+
+    pub fn kids(&self) -> Vec<String> {
+        self.keys().iter().filter_map(|key| key.kid.clone()).collect()
+    }
+
+    /// Reads the discovery document then the JWKS again, the cached keys stay when it fails.
+    /// Blocks on the network, tells whether the kids changed.
+    pub fn refresh(&self) -> Result<bool, KeyError> {
+        let discovery: DiscoveryDocument = serde_json::from_slice(&fetch(&self.discovery_url)?)
+            .map_err(|err| format!("Invalid discovery document {}: {}", self.discovery_url, err))?;
+        if discovery.issuer != self.issuer {
+            return Err(format!("The discovery document {} is for the issuer {}, not {}", self.discovery_url, discovery.issuer, self.issuer).into());
+        }
+
+        let jwks: JwkSet = serde_json::from_slice(&fetch(&discovery.jwks_uri)?)
+            .map_err(|err| format!("Invalid JWKS {}: {}", discovery.jwks_uri, err))?;
+        let keys = Arc::new(jwks_keys(&jwks)?);
+
+        let previous = std::mem::replace(&mut *self.keys.write().expect("OIDC keys lock poisoned"), keys.clone());
+
+        Ok(previous.iter().map(|key| &key.kid).ne(keys.iter().map(|key| &key.kid)))
+    }
+
+    /// Whether the token claims to come from this issuer, it is then verified by `verify` only.
+    pub fn issued(&self, token: &str) -> bool {
+        jsonwebtoken::dangerous::insecure_decode::<UnverifiedIssuer>(token)
+            .is_ok_and(|unverified| unverified.claims.iss.as_deref() == Some(self.issuer.as_str()))
+    }
+
+    /// Checks the token with the issuer keys, its scopes are those of the scope claim.
+    pub fn verify(&self, token: &str) -> Result<Claims, TokenError> {
+        let keys = self.keys();
+        let key = jsonwebtoken::decode_header(token)
+            .map_err(|_| TokenError::Invalid)
+            .and_then(|header| {
+                keys.iter()
+                    .find(|key| key.kid.is_some() && key.kid == header.kid && key.algorithm == header.alg)
+                    .ok_or(TokenError::UnknownKey)
+            });
+
+        let kid = match &key {
+            Ok(key) => key.kid.as_deref().unwrap_or(UNKNOWN_KID),
+            Err(_) => UNKNOWN_KID,
+        };
+        let verified = key.and_then(|key| self.verify_with(key, token));
+
+        let outcome = if verified.is_ok() { "accepted" } else { "rejected" };
+        self.verifications.with_label_values(&[kid, outcome]).inc();
+
+        verified
+    }
+
+    fn verify_with(&self, key: &VerificationKey, token: &str) -> Result<Claims, TokenError> {
+        let mut fields: Map<String, Value> = decode_signed(key, token)?;
+        let scope = scopes_of(&fields, &self.scope_claim);
+
+        // Issuers put lists and objects in `scope` too
+        fields.remove("scope");
+        let claims: Claims = serde_json::from_value(Value::Object(fields)).map_err(|_| TokenError::Invalid)?;
+
+        check_claims(Claims { scope, ..claims }, &self.issuer, &self.audience, self.leeway_seconds)
+    }
+}
+
+/// Space separated scopes of the claim at the dotted path, none when it is missing or of another type.
+pub fn scopes_of(fields: &Map<String, Value>, path: &str) -> String {
+    let mut segments = path.split('.');
+    let first = segments.next().and_then(|segment| fields.get(segment));
+    let claim = segments.fold(first, |claim, segment| claim.and_then(|claim| claim.get(segment)));
+
+    match claim {
+        Some(Value::String(scopes)) => scopes.split_whitespace().collect::<Vec<&str>>().join(" "),
+        Some(Value::Array(scopes)) => scopes.iter().filter_map(Value::as_str).collect::<Vec<&str>>().join(" "),
+        _ => "".to_string(),
+    }
+}
+
+/// Body of an `http://` or `https://` URL, or else of a file.
+fn fetch(location: &str) -> Result<Vec<u8>, KeyError> {
+    if !location.starts_with("http://") && !location.starts_with("https://") {
+        return read(Path::new(location));
+    }
+
+    let response = ureq::get(location)
+        .timeout(FETCH_TIMEOUT)
+        .call()
+        .map_err(|err| format!("Cannot fetch {}: {}", location, err))?;
+
+    let mut body = Vec::new();
+    response.into_reader().take(MAX_DOCUMENT_BYTES).read_to_end(&mut body)?;
+
+    Ok(body)
+}
+
+#[test]
+fn test_scopes_of() {
+    let fields = serde_json::json!({
+        "scope": "openid  quotes:read",
+        "realm_access": {"roles": ["quotes:write", 42, "admin"]},
+        "resource_access": {"playground": "quotes:delete"}
+    });
+    let fields = fields.as_object().unwrap();
+
+    assert_eq!(scopes_of(fields, "scope"), "openid quotes:read");
+    assert_eq!(scopes_of(fields, "realm_access.roles"), "quotes:write admin");
+    assert_eq!(scopes_of(fields, "resource_access.playground"), "quotes:delete");
+    assert_eq!(scopes_of(fields, "realm_access"), "");
+    assert_eq!(scopes_of(fields, "groups"), "");
+}
+
+#[actix_web::test]
+async fn test_oidc_provider() {
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use chrono::Utc;
+    use std::fs;
+    use uuid::Uuid;
+    use crate::http::auth::JwtSettings;
+    use crate::http::keys::{SigningKey, TEST_KEYS};
+
+    let signing_key = |name: &str, kid: &str| SigningKey::from_pem(kid, &fs::read(format!("{}/{}.pem", TEST_KEYS, name)).unwrap()).unwrap();
+    let realm_key = signing_key("rsa", "realm-rsa");
+    let other_key = signing_key("ec", "other-ec");
+
+    // Stub issuer, publishing an encryption key along like Keycloak
+    let mut jwks = serde_json::to_value(JwkSet { keys: vec![realm_key.public_jwk().unwrap().clone()] }).unwrap();
+    jwks["keys"].as_array_mut().unwrap().push(serde_json::json!({"kid": "realm-enc", "kty": "RSA", "alg": "RSA-OAEP", "use": "enc", "n": "AQAB", "e": "AQAB"}));
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let issuer = format!("http://{}/realms/playground", listener.local_addr().unwrap());
+    let discovery = serde_json::json!({"issuer": issuer, "jwks_uri": format!("{}/protocol/openid-connect/certs", issuer)});
+    let server = HttpServer::new(move || {
+        let discovery = discovery.clone();
+        let jwks = jwks.clone();
+
+        App::new()
+            .route("/realms/playground/.well-known/openid-configuration", web::get().to(move || {
+                let discovery = discovery.clone();
+                async move { HttpResponse::Ok().json(discovery) }
+            }))
+            .route("/realms/playground/protocol/openid-connect/certs", web::get().to(move || {
+                let jwks = jwks.clone();
+                async move { HttpResponse::Ok().json(jwks) }
+            }))
+    })
+    .workers(1)
+    .listen(listener)
+    .unwrap()
+    .run();
+    let stub = server.handle();
+    actix_web::rt::spawn(server);
+
+    let verifications = JwtSettings::from_secret("NOT_A_SECRET").verifications;
+    let provider = OidcProvider::new(&issuer, "", "playground", "realm_access.roles", verifications.clone());
+    let refreshed = provider.clone();
+    assert!(web::block(move || refreshed.refresh()).await.unwrap().unwrap());
+    assert_eq!(provider.kids(), vec!["realm-rsa"]);
+
+    let token = |key: &SigningKey, iss: &str, aud: &str, expires_in: i64| key.sign(&serde_json::json!({
+        "iss": iss,
+        "aud": [aud, "account"],
+        "sub": "f3b6c1de",
+        "exp": Utc::now().timestamp() + expires_in,
+        "scope": "openid profile",
+        "realm_access": {"roles": ["quotes:read", "offline_access"]}
+    }));
+
+    let accepted = token(&realm_key, &issuer, "playground", 300);
+    assert!(provider.issued(&accepted));
+    let claims = provider.verify(&accepted).unwrap();
+    assert_eq!(claims.sub.as_deref(), Some("f3b6c1de"));
+    assert_eq!(claims.scope, "quotes:read offline_access");
+    assert!(claims.has_scope("quotes:read") && !claims.has_scope("quotes:write"));
+
+    assert_eq!(provider.verify(&token(&realm_key, &issuer, "other-client", 300)), Err(TokenError::WrongAudience));
+    assert_eq!(provider.verify(&token(&realm_key, &issuer, "playground", -300)), Err(TokenError::Expired));
+    assert_eq!(provider.verify(&token(&other_key, &issuer, "playground", 300)), Err(TokenError::UnknownKey));
+    assert!(!provider.issued(&token(&realm_key, "rust-playground", "playground", 300)));
+    assert!(!provider.issued("not-a-token"));
+    assert_eq!(verifications.with_label_values(&["realm-rsa", "accepted"]).get(), 1);
+    assert_eq!(verifications.with_label_values(&["unknown", "rejected"]).get(), 1);
+
+    // A discovery document in a file, which must announce the issuer
+    let path = std::env::temp_dir().join(format!("openid-configuration-{}.json", Uuid::new_v4()));
+    let provider = OidcProvider::new(&issuer, &path.to_string_lossy(), "", "scope", verifications);
+    fs::write(&path, serde_json::json!({"issuer": "https://sso.example.com", "jwks_uri": format!("{}/protocol/openid-connect/certs", issuer)}).to_string()).unwrap();
+    let refreshed = provider.clone();
+    assert!(web::block(move || refreshed.refresh()).await.unwrap().is_err());
+
+    fs::write(&path, serde_json::json!({"issuer": issuer, "jwks_uri": format!("{}/protocol/openid-connect/certs", issuer)}).to_string()).unwrap();
+    let refreshed = provider.clone();
+    assert!(web::block(move || refreshed.refresh()).await.unwrap().unwrap());
+    assert_eq!(provider.verify(&accepted).unwrap().scope, "openid profile");
+
+    // The keys stay while the issuer is unreachable
+    stub.stop(true).await;
+    let refreshed = provider.clone();
+    assert!(web::block(move || refreshed.refresh()).await.unwrap().is_err());
+    assert_eq!(provider.kids(), vec!["realm-rsa"]);
+
+    fs::remove_file(path).ok();
+}
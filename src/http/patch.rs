@@ -0,0 +1,114 @@
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::HttpRequest;
+use json_patch::Patch;
+use serde_json::Value;
+use crate::db::entities::quote::{ApiPayloadQuote, ApiQuote};
+use crate::http::error::MyError;
+
+pub const MERGE_PATCH: &str = "application/merge-patch+json";
+pub const JSON_PATCH: &str = "application/json-patch+json";
+
+/// Partial update of a quote, applied on its `ApiPayloadQuote` representation.
+#[derive(Debug)]
+pub enum QuotePatch {
+    /// RFC 7396, the fields present replace the current ones, `null` removes them
+    Merge(Value),
+    /// RFC 6902 operations
+    Json(Patch),
+}
+
+impl QuotePatch {
+    /// Reads the patch from the body, its format is given by the `Content-Type`.
+    pub fn parse(req: &HttpRequest, body: &[u8]) -> Result<QuotePatch, MyError> {
+        let content_type = req.headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase());
+
+        match content_type.as_deref() {
+            Some(MERGE_PATCH) => serde_json::from_slice(body)
+                .map(QuotePatch::Merge)
+                .map_err(|err| MyError::InvalidPayload(err.to_string())),
+            Some(JSON_PATCH) => serde_json::from_slice(body)
+                .map(QuotePatch::Json)
+                .map_err(|err| MyError::InvalidPayload(err.to_string())),
+            _ => Err(MyError::UnsupportedMediaType),
+        }
+    }
+
+    /// Payload the quote would have once patched, still to be validated.
+    pub fn apply(&self, current: &ApiQuote) -> Result<ApiPayloadQuote, MyError> {
+        let payload = ApiPayloadQuote {
+            author: current.quote.author.clone(),
+            quote: current.quote.quote.clone(),
+            tags: Some(current.tags.clone()),
+        };
+        // Our own payload always serializes
+        let mut document = serde_json::to_value(payload).map_err(|_| MyError::Internal)?;
+
+        match self {
+            QuotePatch::Merge(merge_patch) => json_patch::merge(&mut document, merge_patch),
+            QuotePatch::Json(json_patch) => json_patch::patch(&mut document, json_patch)
+                .map_err(|err| MyError::InvalidPayload(err.to_string()))?,
+        }
+
+        // Removed tags are cleared, deserialized as absent they would be left untouched
+        if let Some(fields) = document.as_object_mut() {
+            if fields.get("tags").is_none_or(Value::is_null) {
+                fields.insert("tags".to_string(), Value::Array(Vec::new()));
+            }
+        }
+
+        serde_json::from_value(document).map_err(|err| MyError::InvalidPayload(err.to_string()))
+    }
+}
+
+#[test]
+fn test_apply() {
+    use chrono::Utc;
+    use crate::db::entities::quote::Quote;
+
+    let current = ApiQuote {
+        quote: Quote {
+            id: "22e78eeb-3729-431e-aa80-60aea434088e".to_string(),
+            author: "Kaamelott Perceval".to_string(),
+            quote: "C'est pas faux".to_string(),
+            author_id: "perceval".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            version: 1,
+            created_by: None,
+        },
+        tags: vec!["kaamelott".to_string()],
+    };
+
+    let merge = QuotePatch::Merge(serde_json::json!({"author": "Perceval de Galles"}));
+    let patched = merge.apply(&current).unwrap();
+    assert_eq!(patched.author, "Perceval de Galles");
+    assert_eq!(patched.quote, "C'est pas faux");
+    assert_eq!(patched.tags, Some(vec!["kaamelott".to_string()]));
+
+    let merge = QuotePatch::Merge(serde_json::json!({"quote": null}));
+    assert!(merge.apply(&current).is_err());
+
+    let merge = QuotePatch::Merge(serde_json::json!({"tags": null}));
+    assert_eq!(merge.apply(&current).unwrap().tags, Some(vec![]));
+
+    let json: Patch = serde_json::from_value(serde_json::json!([{"op": "remove", "path": "/tags"}])).unwrap();
+    assert_eq!(QuotePatch::Json(json).apply(&current).unwrap().tags, Some(vec![]));
+
+    let json: Patch = serde_json::from_value(serde_json::json!([
+        {"op": "add", "path": "/tags/-", "value": "table ronde"},
+        {"op": "replace", "path": "/quote", "value": "Cuillère"},
+    ])).unwrap();
+    let patched = QuotePatch::Json(json).apply(&current).unwrap();
+    assert_eq!(patched.quote, "Cuillère");
+    assert_eq!(patched.tags, Some(vec!["kaamelott".to_string(), "table ronde".to_string()]));
+
+    let json: Patch = serde_json::from_value(serde_json::json!([
+        {"op": "test", "path": "/quote", "value": "Autre chose"},
+    ])).unwrap();
+    assert!(QuotePatch::Json(json).apply(&current).is_err());
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use chrono::Utc;
+use crate::db::entities::revoked_token::RevokedToken;
+use crate::http::auth::{Claims, TokenError};
+
+/// In-process copy of the `revoked_tokens` table, so that the validator never waits on the database.
+///
+/// Entries are only added, by `POST /auth/revoke` and by the refresh job picking up the
+/// revocations of the other instances, and pruned once their token expired.
+#[derive(Debug, Default)]
+pub struct RevocationList {
+    /// `exp` of the revoked tokens by `jti`
+    revoked: RwLock<HashMap<String, i64>>,
+}
+
+impl RevocationList {
+    pub fn revoke(&self, revoked_tokens: &[RevokedToken]) {
+        let mut revoked = self.revoked.write().expect("revocation list lock poisoned");
+
+        for revoked_token in revoked_tokens {
+            revoked.insert(revoked_token.jti.clone(), revoked_token.expires_at.timestamp());
+        }
+    }
+
+    pub fn is_revoked(&self, jti: &str) -> bool {
+        self.revoked.read().expect("revocation list lock poisoned").contains_key(jti)
+    }
+
+    /// Refuses the claims of a revoked token, tokens without `jti` cannot be revoked.
+    pub fn check(&self, claims: Claims) -> Result<Claims, TokenError> {
+        match claims.jti.as_deref() {
+            Some(jti) if self.is_revoked(jti) => Err(TokenError::Revoked),
+            _ => Ok(claims),
+        }
+    }
+
+    /// Forgets the tokens expired more than `leeway_seconds` ago, the verification refuses them anyway.
+    pub fn prune(&self, leeway_seconds: i64) -> usize {
+        let expired_before = Utc::now().timestamp() - leeway_seconds;
+        let mut revoked = self.revoked.write().expect("revocation list lock poisoned");
+        let count = revoked.len();
+
+        revoked.retain(|_, expires_at| *expires_at > expired_before);
+
+        count - revoked.len()
+    }
+}
+
+#[test]
+fn test_revocation_list() {
+    use chrono::Duration;
+
+    let revoked_token = |jti: &str, expires_in: i64| RevokedToken {
+        jti: jti.to_string(),
+        subject: Some("batch".to_string()),
+        expires_at: Utc::now() + Duration::seconds(expires_in),
+        revoked_at: Utc::now(),
+    };
+    let claims = |jti: Option<&str>| Claims { jti: jti.map(str::to_string), ..Claims::default() };
+
+    let revocations = RevocationList::default();
+    revocations.revoke(&[revoked_token("expired", -120), revoked_token("valid", 600)]);
+
+    assert_eq!(revocations.check(claims(Some("valid"))), Err(TokenError::Revoked));
+    assert!(revocations.check(claims(Some("other"))).is_ok());
+    assert!(revocations.check(claims(None)).is_ok());
+
+    // Kept while the leeway still accepts the token
+    assert_eq!(revocations.prune(300), 0);
+    assert_eq!(revocations.prune(60), 1);
+    assert!(!revocations.is_revoked("expired") && revocations.is_revoked("valid"));
+}
@@ -0,0 +1,149 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::db::entities::api_key::{hash_key, random_secret};
+use crate::db::entities::refresh_token::RefreshToken;
+use crate::db::entities::user::{hash_password, verify_password, User};
+use crate::db::pool::DbPool;
+use crate::db::repositories::refresh_token::RefreshTokenRepository;
+use crate::db::repositories::user::UserRepository;
+use crate::http::error::MyError;
+
+/// Prefix of the refresh tokens, so that leaked tokens are easy to spot.
+const REFRESH_TOKEN_PREFIX: &str = "rpr_";
+
+/// Username and password exchanged at `POST /auth/login`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiLoginRequest {
+    #[schema(example = "haddock")]
+    pub username: String,
+    pub password: String,
+}
+
+/// Refresh token exchanged at `POST /auth/refresh`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiRefreshRequest {
+    pub refresh_token: String,
+}
+
+/// Caller of `GET /auth/me`, as told by its credentials.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiMe {
+    pub sub: Option<String>,
+    /// Space separated scopes
+    pub scope: String,
+    /// Account of the subject, absent for clients and API keys
+    pub user: Option<User>,
+}
+
+/// Stores a new refresh token of the family, returns the token itself.
+fn start_refresh_token(user_id: &str, family_id: &str, ttl_seconds: i64, connection: &mut PgConnection) -> QueryResult<String> {
+    let token = random_secret(REFRESH_TOKEN_PREFIX);
+    let now = Utc::now();
+
+    RefreshTokenRepository.insert(&RefreshToken {
+        id: Uuid::new_v4().to_string(),
+        token_hash: hash_key(&token),
+        user_id: user_id.to_string(),
+        family_id: family_id.to_string(),
+        created_at: now,
+        expires_at: now + Duration::seconds(ttl_seconds),
+        used_at: None,
+        revoked_at: None,
+    }, connection)?;
+
+    Ok(token)
+}
+
+/// Checks the password, then starts a refresh token family. Blocks on the database and Argon2.
+pub fn login(credentials: &ApiLoginRequest, refresh_ttl_seconds: i64, pool: &DbPool) -> Result<(User, String), MyError> {
+    let mut conn = pool.get()?;
+
+    let user = UserRepository.get_user_by_username(&credentials.username, &mut conn).optional()?;
+    let user = match user {
+        Some(user) if verify_password(&credentials.password, &user.password_hash) => user,
+        Some(_) => return Err(MyError::InvalidCredentials),
+        None => {
+            // As slow as a wrong password, so that usernames cannot be told apart by timing
+            let _ = hash_password(&credentials.password);
+            return Err(MyError::InvalidCredentials);
+        },
+    };
+
+    let refresh_token = start_refresh_token(&user.id, &Uuid::new_v4().to_string(), refresh_ttl_seconds, &mut conn)?;
+
+    Ok((user, refresh_token))
+}
+
+/// Exchanges the refresh token for the next one of its family.
+///
+/// A token exchanged twice was replayed, by the thief or by the client it was stolen from, so
+/// the whole family is revoked and both have to log in again.
+pub fn refresh(refresh_token: &str, refresh_ttl_seconds: i64, pool: &DbPool) -> Result<(User, String), MyError> {
+    let refresh_token_repository = RefreshTokenRepository;
+    let now = Utc::now();
+    let mut conn = pool.get()?;
+
+    let current = refresh_token_repository.get_by_hash(&hash_key(refresh_token), &mut conn)
+        .optional()?
+        .ok_or(MyError::InvalidGrant)?;
+    if current.revoked_at.is_some() || current.expires_at <= now {
+        return Err(MyError::InvalidGrant);
+    }
+
+    // Marked atomically, of two concurrent exchanges only one goes through
+    if refresh_token_repository.mark_used(&current.id, now, &mut conn)? == 0 {
+        warn!("Refresh token {} of user {} reused, revoking its family {}", current.id, current.user_id, current.family_id);
+        refresh_token_repository.revoke_family(&current.family_id, now, &mut conn)?;
+        return Err(MyError::InvalidGrant);
+    }
+
+    let user = UserRepository.get_user(&current.user_id, &mut conn)?;
+    let next = start_refresh_token(&user.id, &current.family_id, refresh_ttl_seconds, &mut conn)?;
+
+    Ok((user, next))
+}
+
+#[test]
+fn test_login_and_refresh() {
+    use dotenv::dotenv;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let username = format!("haddock-{}", Uuid::new_v4());
+    UserRepository.insert(&User {
+        id: Uuid::new_v4().to_string(),
+        username: username.clone(),
+        password_hash: hash_password("Tonnerre de Brest").unwrap(),
+        scope: "quotes:read".to_string(),
+        created_at: Utc::now(),
+    }, &mut pool.get().unwrap()).unwrap();
+    let credentials = |username: &str, password: &str| ApiLoginRequest { username: username.to_string(), password: password.to_string() };
+
+    assert!(matches!(login(&credentials(&username, "Mille sabords"), 60, &pool), Err(MyError::InvalidCredentials)));
+    assert!(matches!(login(&credentials("nobody", "Tonnerre de Brest"), 60, &pool), Err(MyError::InvalidCredentials)));
+
+    let (user, first) = login(&credentials(&username, "Tonnerre de Brest"), 60, &pool).unwrap();
+    assert_eq!(user.username, username);
+
+    // Rotated on every exchange
+    let (_, second) = refresh(&first, 60, &pool).unwrap();
+    let (_, third) = refresh(&second, 60, &pool).unwrap();
+    assert!(first != second && second != third);
+
+    // Reusing a rotated token revokes the tokens of the family
+    assert!(matches!(refresh(&first, 60, &pool), Err(MyError::InvalidGrant)));
+    assert!(matches!(refresh(&third, 60, &pool), Err(MyError::InvalidGrant)));
+
+    // Other logins are not affected, expired tokens are refused
+    let (_, other) = login(&credentials(&username, "Tonnerre de Brest"), 60, &pool).unwrap();
+    assert!(refresh(&other, 60, &pool).is_ok());
+    let (_, expired) = login(&credentials(&username, "Tonnerre de Brest"), -1, &pool).unwrap();
+    assert!(matches!(refresh(&expired, 60, &pool), Err(MyError::InvalidGrant)));
+    assert!(matches!(refresh("rpr_unknown", 60, &pool), Err(MyError::InvalidGrant)));
+}
@@ -0,0 +1,31 @@
+use std::time::Duration;
+use actix_web::{rt, web};
+use log::{error, info};
+use crate::http::auth::JwtSettings;
+
+/// Reads the JWT keys again every `interval_seconds` on the current actix runtime.
+pub fn spawn_reload_keyring(jwt: JwtSettings, interval_seconds: usize) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(Duration::from_secs(interval_seconds.max(1) as u64));
+        // The first tick completes immediately, the keys were just loaded
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            let jwt = jwt.clone();
+            // A broken keyring keeps the current keys until it is fixed
+            let reloaded = web::block(move || jwt.reload().map(|changed| (changed, jwt))).await;
+
+            match reloaded {
+                Ok(Ok((false, _))) => {},
+                Ok(Ok((true, jwt))) => {
+                    let keyring = jwt.keyring();
+                    info!("Reloaded the JWT keyring, signing with {:?}, verifying {:?}", keyring.signing_key.kid, keyring.kids())
+                },
+                Ok(Err(err)) => error!("Failed to reload the JWT keyring: {}", err),
+                Err(err) => error!("Failed to reload the JWT keyring: {}", err),
+            }
+        }
+    });
+}
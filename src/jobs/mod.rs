@@ -0,0 +1,4 @@
+pub mod keyring;
+pub mod oidc;
+pub mod purge;
+pub mod revocations;
@@ -0,0 +1,28 @@
+use std::time::Duration;
+use actix_web::{rt, web};
+use log::{error, info};
+use crate::http::oidc::OidcProvider;
+
+/// Reads the keys of the OpenID Connect issuer again every `interval_seconds` on the current actix runtime.
+pub fn spawn_refresh_oidc_keys(oidc: OidcProvider, interval_seconds: usize) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(Duration::from_secs(interval_seconds.max(1) as u64));
+        // The first tick completes immediately, the keys were just loaded
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            let oidc = oidc.clone();
+            // An unreachable issuer keeps the cached keys until it is back
+            let refreshed = web::block(move || oidc.refresh().map(|changed| (changed, oidc))).await;
+
+            match refreshed {
+                Ok(Ok((false, _))) => {},
+                Ok(Ok((true, oidc))) => info!("Refreshed the keys of {}, verifying {:?}", oidc.issuer, oidc.kids()),
+                Ok(Err(err)) => error!("Failed to refresh the OIDC issuer keys: {}", err),
+                Err(err) => error!("Failed to refresh the OIDC issuer keys: {}", err),
+            }
+        }
+    });
+}
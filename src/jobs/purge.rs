@@ -0,0 +1,56 @@
+use std::time::Duration;
+use actix_web::{rt, web};
+use chrono::Utc;
+use log::{error, info};
+use crate::db::store::{QuoteStore, SharedQuoteStore};
+use crate::http::error::MyError;
+
+/// Hard deletes the quotes soft deleted more than `retention_days` ago.
+pub fn purge_deleted_quotes(retention_days: usize, store: &dyn QuoteStore) -> Result<usize, MyError> {
+    let deleted_before = Utc::now() - chrono::Duration::days(retention_days as i64);
+
+    store.purge_deleted_quotes(deleted_before)
+}
+
+/// Runs `purge_deleted_quotes` every `interval_seconds` on the current actix runtime.
+pub fn spawn_purge_deleted_quotes(store: SharedQuoteStore, retention_days: usize, interval_seconds: usize) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(Duration::from_secs(interval_seconds.max(1) as u64));
+
+        loop {
+            interval.tick().await;
+
+            let store = store.clone();
+            // A failed run, e.g. with the database unavailable, is retried on the next tick
+            let purged = web::block(move || purge_deleted_quotes(retention_days, store.as_ref())).await;
+
+            match purged {
+                Ok(Ok(0)) => {},
+                Ok(Ok(count)) => info!("Purged {} quotes deleted more than {} days ago", count, retention_days),
+                Ok(Err(err)) => error!("Failed to purge deleted quotes: {}", err),
+                Err(err) => error!("Failed to purge deleted quotes: {}", err),
+            }
+        }
+    });
+}
+
+#[test]
+fn test_purge_deleted_quotes() {
+    use crate::db::entities::quote::ApiPayloadQuote;
+    use crate::db::store::memory::MemoryQuoteStore;
+
+    let store = MemoryQuoteStore::default();
+    let quote = store.create_quote(&ApiPayloadQuote {
+        author: "Tryphon Tournesol".to_string(),
+        quote: "Un peu plus a l'ouest".to_string(),
+        tags: None,
+    }, None).unwrap();
+    store.delete_quote(&quote.quote.id, None).unwrap();
+
+    assert_eq!(purge_deleted_quotes(1, &store).unwrap(), 0);
+    assert!(store.get_quote(&quote.quote.id, true).is_ok());
+
+    // Without retention every deleted quote goes
+    assert_eq!(purge_deleted_quotes(0, &store).unwrap(), 1);
+    assert!(store.get_quote(&quote.quote.id, true).is_err());
+}
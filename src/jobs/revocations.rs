@@ -0,0 +1,79 @@
+use std::time::Duration;
+use actix_web::{rt, web};
+use chrono::Utc;
+use log::{error, info};
+use crate::db::pool::DbPool;
+use crate::db::repositories::revoked_token::RevokedTokenRepository;
+use crate::http::error::MyError;
+use crate::http::revocation::RevocationList;
+
+/// Deletes the revocations of the tokens expired more than `leeway_seconds` ago, then caches the
+/// remaining ones. Returns how many were pruned.
+pub fn refresh_revocations(revocations: &RevocationList, leeway_seconds: i64, pool: &DbPool) -> Result<usize, MyError> {
+    let revoked_token_repository = RevokedTokenRepository;
+    let expired_before = Utc::now() - chrono::Duration::seconds(leeway_seconds);
+    let mut conn = pool.get()?;
+
+    let pruned = revoked_token_repository.prune(expired_before, &mut conn)?;
+    revocations.revoke(&revoked_token_repository.get_expiring_after(expired_before, &mut conn)?);
+    revocations.prune(leeway_seconds);
+
+    Ok(pruned)
+}
+
+/// Runs `refresh_revocations` every `interval_seconds` on the current actix runtime, starting now.
+pub fn spawn_refresh_revocations(revocations: web::Data<RevocationList>, pool: DbPool, leeway_seconds: i64, interval_seconds: usize) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(Duration::from_secs(interval_seconds.max(1) as u64));
+
+        loop {
+            interval.tick().await;
+
+            let revocations = revocations.clone();
+            let pool = pool.clone();
+            // Without database the cached revocations still apply, the refresh is retried on the next tick
+            let refreshed = web::block(move || refresh_revocations(&revocations, leeway_seconds, &pool)).await;
+
+            match refreshed {
+                Ok(Ok(0)) => {},
+                Ok(Ok(count)) => info!("Pruned {} revoked tokens past their expiry", count),
+                Ok(Err(err)) => error!("Failed to refresh the revoked tokens: {}", err),
+                Err(err) => error!("Failed to refresh the revoked tokens: {}", err),
+            }
+        }
+    });
+}
+
+#[test]
+fn test_refresh_revocations() {
+    use dotenv::dotenv;
+    use uuid::Uuid;
+    use crate::db::entities::revoked_token::RevokedToken;
+
+    dotenv().ok();
+    let pool = crate::db::pool::build_db_pool(
+        std::env::var("DATABASE_URL").expect("No DATABASE_URL configured")
+    );
+    let revoked_token = |expires_in: i64| RevokedToken {
+        jti: Uuid::new_v4().to_string(),
+        subject: Some("batch".to_string()),
+        expires_at: Utc::now() + chrono::Duration::seconds(expires_in),
+        revoked_at: Utc::now(),
+    };
+
+    // Revoked by another instance
+    let expired = revoked_token(-120);
+    let valid = revoked_token(600);
+    for revoked in [&expired, &valid] {
+        RevokedTokenRepository.insert(revoked, &mut pool.get().unwrap()).unwrap();
+    }
+
+    let revocations = RevocationList::default();
+    assert!(refresh_revocations(&revocations, 60, &pool).unwrap() >= 1);
+    assert!(revocations.is_revoked(&valid.jti));
+    assert!(!revocations.is_revoked(&expired.jti));
+
+    let remaining = RevokedTokenRepository.get_expiring_after(Utc::now() - chrono::Duration::days(1), &mut pool.get().unwrap()).unwrap();
+    let remaining: Vec<String> = remaining.into_iter().map(|found| found.jti).collect();
+    assert!(remaining.contains(&valid.jti) && !remaining.contains(&expired.jti));
+}
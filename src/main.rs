@@ -1,35 +1,39 @@
 mod http;
 mod db;
 mod config;
+mod jobs;
+mod cli;
 
 #[macro_use]
 extern crate diesel;
 
 extern crate dotenv;
 
-use crate::config::env::load_config_from_env;
-use crate::db::pool::build_db_pool;
+use crate::config::env::{load_config_from_env, Config};
+use crate::db::pool::DbPool;
+use crate::db::store::QuoteStore;
+use clap::Parser;
 use dotenv::dotenv;
 use log::info;
-use std::{env, collections::BTreeMap};
-
-use hmac::{Hmac, Mac};
-use jwt::{VerifyWithKey, SignWithKey};
-use sha2::Sha256;
 
 use actix_web::{
     get,
     App, HttpServer, Result, web::{self, Redirect},
     dev::ServiceRequest,
+    HttpMessage,
     Error,
-    middleware::{Logger, DefaultHeaders}, http::{header::ContentType, StatusCode}, Responder, HttpResponse
+    middleware::{Logger, DefaultHeaders, ErrorHandlers}, http::{header::ContentType, StatusCode}, Responder, HttpResponse
 };
 
-use actix_web_httpauth::{extractors::bearer::BearerAuth, middleware::HttpAuthentication};
-use http::error::MyError;
+use actix_web_httpauth::{
+    extractors::{bearer::BearerAuth, AuthenticationError},
+    headers::www_authenticate::bearer::Bearer,
+    middleware::HttpAuthentication,
+};
+use http::{auth::JwtSettings, error::{ApiProblem, MyError}, oidc::OidcProvider, revocation::RevocationList};
 
 use utoipa::{
-    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
     Modify, OpenApi,
 };
 use utoipa_swagger_ui::SwaggerUi;
@@ -37,54 +41,94 @@ use actix_web_prom::PrometheusMetricsBuilder;
 use std::collections::HashMap;
 use gethostname::gethostname;
 
+/// Authenticates the request with its bearer token, or else with its `X-Api-Key` header.
 async fn validator(
     req: ServiceRequest,
-    _credentials: BearerAuth,
+    credentials: Option<BearerAuth>,
 ) -> Result<ServiceRequest, (Error, ServiceRequest)> {
     if req.path().contains("/swagger-ui") {
         return Ok(req);
     }
 
-    let token = _credentials.token();
-
-    let key: Hmac<Sha256> = Hmac::new_from_slice(
-        env::var("JWT_SECRET").unwrap_or("JWT_SECRET".to_string()).as_ref()
-    ).unwrap();
+    let api_key = req.headers()
+        .get(http::auth::API_KEY_HEADER)
+        .and_then(|api_key| api_key.to_str().ok())
+        .map(str::to_string);
+
+    let verified = match (credentials, api_key) {
+        (Some(credentials), _) => verify_token(&req, credentials.token()),
+        (None, Some(api_key)) => match req.app_data::<web::Data<DbPool>>() {
+            Some(pool) => {
+                let pool = pool.clone();
+                web::block(move || http::auth::authenticate_api_key(&api_key, &pool))
+                    .await
+                    .map_err(MyError::from)
+                    .and_then(|authenticated| authenticated)
+            },
+            // API keys are stored in Postgres only
+            None => Err(MyError::NotImplemented("API keys need the postgres store, send a bearer token instead".to_string())),
+        },
+        // Same challenge as the bearer extractor
+        (None, None) => return Err((AuthenticationError::new(Bearer::default()).into(), req)),
+    };
+
+    match verified {
+        Ok(claims) => {
+            req.extensions_mut().insert(claims);
+            Ok(req)
+        },
+        Err(err) => Err((Error::from(err), req)),
+    }
+}
 
-    let verify_promise: Result<BTreeMap<String, String>, jwt::Error> = token.verify_with_key(&key);
+/// Tokens of the OpenID Connect issuer, when one is configured, are verified with its keys only.
+fn verify_token(req: &ServiceRequest, token: &str) -> Result<http::auth::Claims, MyError> {
+    let oidc = req.app_data::<web::Data<OidcProvider>>().filter(|oidc| oidc.issued(token));
 
-    if verify_promise.is_err() {
-        return Err((Error::from(MyError::Unauthorized), req));
+    match (req.app_data::<web::Data<JwtSettings>>(), req.app_data::<web::Data<RevocationList>>()) {
+        (Some(jwt), Some(revocations)) => oidc.map_or_else(|| jwt.verify(token), |oidc| oidc.verify(token))
+            .and_then(|claims| revocations.check(claims))
+            .map_err(|err| MyError::InvalidToken(err.to_string())),
+        _ => Err(MyError::Internal),
     }
-
-    Ok(req)
 }
 
+/// Whether the store schema is up to date, otherwise why the instance should not get traffic.
+async fn check_migrations(store: web::Data<dyn QuoteStore>) -> Result<(), String> {
+    let pending = web::block(move || store.pending_migrations())
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| format!("Migration status unavailable: {}", err))?;
 
-fn create_jwt() -> String {
-    let key: Hmac<Sha256> = Hmac::new_from_slice(
-        env::var("JWT_SECRET").unwrap_or("JWT_SECRET".to_string()).as_ref()
-    ).unwrap();
-    let mut claims = BTreeMap::new();
-    claims.insert("audiance", "127.0.0.1");
-
-    claims.sign_with_key(&key).unwrap()
+    match pending.is_empty() {
+        true => Ok(()),
+        false => Err(format!("Pending migrations: {}", pending.join(", "))),
+    }
 }
 
 #[get("/health")]
-async fn health() -> impl Responder {
-    HttpResponse::Ok()
-        .status(StatusCode::OK)
+async fn health(store: web::Data<dyn QuoteStore>) -> impl Responder {
+    let (status, body) = match check_migrations(store).await {
+        Ok(()) => (StatusCode::OK, "Ok".to_string()),
+        Err(reason) => (StatusCode::SERVICE_UNAVAILABLE, reason),
+    };
+
+    HttpResponse::build(status)
         .content_type(ContentType::plaintext())
-        .body("Ok")
+        .body(body)
 }
 
 #[get("/health")]
-async fn health_json() -> impl Responder {
-    HttpResponse::Ok()
-        .status(StatusCode::OK)
-        .content_type(ContentType::json())
-        .json("Ok")
+async fn health_json(store: web::Data<dyn QuoteStore>) -> impl Responder {
+    match check_migrations(store).await {
+        Ok(()) => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .json("Ok"),
+        Err(reason) => ApiProblem {
+            detail: Some(reason),
+            ..ApiProblem::new(StatusCode::SERVICE_UNAVAILABLE)
+        }.response(),
+    }
 }
 
 #[actix_web::main]
@@ -93,12 +137,58 @@ async fn main() -> std::io::Result<()> {
 
     simple_logger::init_with_env().unwrap();
 
+    let cli = cli::Cli::parse();
     let config = load_config_from_env();
 
-    info!("Config: {}", config.to_string());
-    info!("JWT: {}", create_jwt());
+    match cli.command.unwrap_or(cli::Command::Serve) {
+        cli::Command::Serve => serve(config).await,
+        command => {
+            if let Err(err) = cli::run(command, &config) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+
+            Ok(())
+        },
+    }
+}
+
+async fn serve(config: Config) -> std::io::Result<()> {
+    info!("Config: {}", config);
+
+    let (pool, store) = db::store::from_config(&config);
+    let jwt = JwtSettings::from_config(&config).map_err(std::io::Error::other)?;
+    let token_issuer = web::Data::new(http::auth::TokenIssuer::from_config(&config, jwt.clone()));
+    let oidc = OidcProvider::from_config(&config, jwt.verifications.clone())
+        .map_err(std::io::Error::other)?
+        .map(web::Data::new);
+    let jwt = web::Data::new(jwt);
+    let revocations = web::Data::new(RevocationList::default());
+
+    if config.database_auto_migrate {
+        let applied = store.run_pending_migrations().map_err(std::io::Error::other)?;
+        info!("Applied {} pending migrations {:?}", applied.len(), applied);
+    }
 
-    let pool = build_db_pool(config.database_url.to_string());
+    jobs::purge::spawn_purge_deleted_quotes(
+        store.clone(),
+        config.quotes_retention_days,
+        config.quotes_purge_interval
+    );
+    jobs::keyring::spawn_reload_keyring(jwt.get_ref().clone(), config.jwt_keyring_reload_interval);
+    if let Some(oidc) = &oidc {
+        info!("Accepting the tokens of {}, verifying {:?}", oidc.issuer, oidc.kids());
+        jobs::oidc::spawn_refresh_oidc_keys(oidc.get_ref().clone(), config.oidc_refresh_interval);
+    }
+    match &pool {
+        Some(pool) => jobs::revocations::spawn_refresh_revocations(
+            revocations.clone(),
+            pool.clone(),
+            jwt.leeway_seconds,
+            config.jwt_revocation_refresh_interval
+        ),
+        None => info!("Token revocation needs the postgres store, /auth/revoke is off"),
+    }
 
     struct SecurityAddon;
 
@@ -113,6 +203,10 @@ async fn main() -> std::io::Result<()> {
                         .bearer_format("JWT")
                         .build(),
                 ),
+            );
+            components.add_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(http::auth::API_KEY_HEADER))),
             )
         }
     }
@@ -121,16 +215,63 @@ async fn main() -> std::io::Result<()> {
     #[openapi(
         modifiers(&SecurityAddon),
         paths(
+            http::controllers::auth::token,
+            http::controllers::auth::login,
+            http::controllers::auth::refresh,
+            http::controllers::auth::me,
+            http::controllers::auth::revoke,
+            http::controllers::auth::jwks,
             http::controllers::quotes::list,
+            http::controllers::quotes::search,
+            http::controllers::quotes::random,
+            http::controllers::quotes::daily,
+            http::controllers::quotes::pin_daily,
             http::controllers::quotes::item,
             http::controllers::quotes::add,
             http::controllers::quotes::update,
+            http::controllers::quotes::patch,
             http::controllers::quotes::delete,
+            http::controllers::quotes::restore,
+            http::controllers::quotes::revisions,
+            http::controllers::quotes::revision_diff,
+            http::controllers::quotes::revert,
+            http::controllers::authors::list,
+            http::controllers::authors::item,
+            http::controllers::authors::add,
+            http::controllers::authors::update,
+            http::controllers::authors::delete,
+            http::controllers::api_keys::list,
+            http::controllers::api_keys::add,
+            http::controllers::api_keys::revoke,
         ),
         components(
             schemas(
                 db::entities::quote::Quote,
-                db::entities::quote::ApiPayloadQuote
+                db::entities::quote::ApiQuote,
+                db::entities::quote::ApiPayloadQuote,
+                db::entities::quote::ApiQuotePage,
+                db::entities::quote::QuoteSortField,
+                db::entities::quote::SortOrder,
+                db::entities::daily_quote::DailyQuote,
+                db::entities::daily_quote::ApiPayloadDailyQuote,
+                db::entities::author::Author,
+                db::entities::author::ApiPayloadAuthor,
+                db::entities::author::ApiAuthorPage,
+                db::entities::tag::TagMode,
+                db::entities::quote_revision::QuoteRevision,
+                db::entities::quote_revision::ApiFieldChange,
+                db::entities::quote_revision::ApiRevisionDiff,
+                http::auth::ApiTokenRequest,
+                http::auth::ApiRevokeRequest,
+                http::sessions::ApiLoginRequest,
+                http::sessions::ApiRefreshRequest,
+                http::sessions::ApiMe,
+                db::entities::user::User,
+                http::auth::ApiToken,
+                db::entities::api_key::ApiKey,
+                db::entities::api_key::ApiPayloadApiKey,
+                db::entities::api_key::ApiCreatedApiKey,
+                http::error::ApiProblem
             )
         )
     )]
@@ -138,24 +279,41 @@ async fn main() -> std::io::Result<()> {
 
     let openapi = ApiDoc::openapi();
 
+    let mut labels = HashMap::new();
+    labels.insert(
+        "host".to_string(),
+        format!("{:?}", gethostname())
+    );
+    // Built once so that every worker shares the registry, with the JWT verifications
+    let prometheus = PrometheusMetricsBuilder::new(&config.prometheus_namespace)
+        .endpoint(&config.prometheus_metrics_path)
+        .const_labels(labels)
+        .build()
+        .unwrap();
+    prometheus.registry
+        .register(Box::new(jwt.verifications.clone()))
+        .map_err(std::io::Error::other)?;
+
     info!("Start Server on port {}", config.http_listen_port);
 
     HttpServer::new(move || {
-        let auth = HttpAuthentication::bearer(validator);
-
-        let mut labels = HashMap::new();
-        labels.insert(
-            "host".to_string(),
-            format!("{:?}", gethostname())
-        );
-        let prometheus = PrometheusMetricsBuilder::new(&config.prometheus_namespace)
-            .endpoint(&config.prometheus_metrics_path)
-            .const_labels(labels)
-            .build()
-            .unwrap();
+        let auth = HttpAuthentication::with_fn(validator);
 
         App::new()
-            .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::from(store.clone()))
+            .app_data(jwt.clone())
+            .app_data(token_issuer.clone())
+            .app_data(revocations.clone())
+            .configure(|cfg| {
+                if let Some(pool) = &pool {
+                    cfg.app_data(web::Data::new(pool.clone()));
+                }
+                if let Some(oidc) = &oidc {
+                    cfg.app_data(oidc.clone());
+                }
+            })
+            .app_data(web::JsonConfig::default().error_handler(http::error::json_error_handler))
+            .app_data(web::QueryConfig::default().error_handler(http::error::query_error_handler))
             .wrap(prometheus.clone())
             .service(health)
 
@@ -163,6 +321,32 @@ async fn main() -> std::io::Result<()> {
             .wrap(Logger::default())
             .wrap(Logger::new("%a %{User-Agent}i"))
 
+            .service(http::controllers::auth::jwks)
+            .service(
+                web::scope("/auth")
+                        .wrap(ErrorHandlers::new().default_handler(http::error::problem_details))
+                        .service(http::controllers::auth::token)
+                        // Users and their refresh tokens are stored in Postgres only
+                        .configure(|cfg| {
+                            if pool.is_some() {
+                                cfg.service(http::controllers::auth::login)
+                                    .service(http::controllers::auth::refresh);
+                            }
+                        })
+                        // after the routes which need no credentials
+                        .service(
+                            web::scope("")
+                                .wrap(auth.clone())
+                                // Revocations are stored in Postgres only
+                                .configure(|cfg| {
+                                    if pool.is_some() {
+                                        cfg.service(http::controllers::auth::revoke);
+                                    }
+                                })
+                                .service(http::controllers::auth::me)
+                        )
+            )
+
             .service(
                 web::scope("/api")
                         // api format
@@ -170,12 +354,35 @@ async fn main() -> std::io::Result<()> {
 
                         // auth
                         .wrap(auth)
+                        // errors, outermost so that authentication failures are problems too
+                        .wrap(ErrorHandlers::new().default_handler(http::error::problem_details))
                         // routes
                         .service(http::controllers::quotes::list)
+                        .service(http::controllers::quotes::search)
+                        .service(http::controllers::quotes::random)
+                        .service(http::controllers::quotes::daily)
+                        .service(http::controllers::quotes::pin_daily)
                         .service(http::controllers::quotes::item)
                         .service(http::controllers::quotes::delete)
+                        .service(http::controllers::quotes::restore)
+                        .service(http::controllers::quotes::revisions)
+                        .service(http::controllers::quotes::revision_diff)
+                        .service(http::controllers::quotes::revert)
                         .service(http::controllers::quotes::add)
                         .service(http::controllers::quotes::update)
+                        .service(http::controllers::quotes::patch)
+                        .service(http::controllers::authors::list)
+                        .service(http::controllers::authors::item)
+                        .service(http::controllers::authors::delete)
+                        .service(http::controllers::authors::add)
+                        .service(http::controllers::authors::update)
+                        .configure(|cfg| {
+                            if pool.is_some() {
+                                cfg.service(http::controllers::api_keys::list)
+                                    .service(http::controllers::api_keys::add)
+                                    .service(http::controllers::api_keys::revoke);
+                            }
+                        })
                         .service(health_json)
 
             )
@@ -208,12 +415,19 @@ async fn main() -> std::io::Result<()> {
 #[actix_web::test]
 async fn test_index_without_jwt() {
     use actix_web::test;
+    use std::sync::Arc;
+    use crate::db::store::SharedQuoteStore;
+    use crate::db::store::memory::MemoryQuoteStore;
     use actix_web::http::StatusCode;
 
     dotenv().ok();
-    let auth = HttpAuthentication::bearer(validator);
+    let auth = HttpAuthentication::with_fn(validator);
+    let store: SharedQuoteStore = Arc::new(MemoryQuoteStore::default());
     let app = test::init_service(
         App::new()
+            .app_data(web::Data::from(store))
+            .app_data(web::Data::new(JwtSettings::from_config(&load_config_from_env()).unwrap()))
+            .app_data(web::Data::new(RevocationList::default()))
             .wrap(auth)
             .service(health_json)
     ).await;
@@ -227,18 +441,26 @@ async fn test_index_without_jwt() {
 #[actix_web::test]
 async fn test_index_with_jwt() {
     use actix_web::test;
+    use std::sync::Arc;
+    use crate::db::store::SharedQuoteStore;
+    use crate::db::store::memory::MemoryQuoteStore;
 
     dotenv().ok();
-    let auth = HttpAuthentication::bearer(validator);
+    let auth = HttpAuthentication::with_fn(validator);
+    let jwt = JwtSettings::from_config(&load_config_from_env()).unwrap();
 
+    let store: SharedQuoteStore = Arc::new(MemoryQuoteStore::default());
     let app = test::init_service(
         App::new()
+            .app_data(web::Data::from(store))
+            .app_data(web::Data::new(jwt.clone()))
+            .app_data(web::Data::new(RevocationList::default()))
             .wrap(auth)
             .service(health_json)
     ).await;
     let req = test::TestRequest::get().uri("/health")
         .insert_header(ContentType::json())
-        .insert_header(("Authorization", format!("Bearer {}" ,create_jwt())))
+        .insert_header(("Authorization", format!("Bearer {}", jwt.sign("test", &["admin".to_string()], 60))))
         .to_request();
     let resp = test::call_service(&app, req).await;
     let status = resp.status();
@@ -246,3 +468,264 @@ async fn test_index_with_jwt() {
     println!("Out: {:?} Status: {}", std::str::from_utf8(&body), status.as_str());
     assert!(status.is_success());
 }
+
+#[actix_web::test]
+async fn test_index_with_oidc_jwt() {
+    use actix_web::test;
+    use actix_web::http::header;
+    use chrono::Utc;
+    use jsonwebtoken::jwk::JwkSet;
+    use std::sync::Arc;
+    use uuid::Uuid;
+    use crate::db::store::SharedQuoteStore;
+    use crate::db::store::memory::MemoryQuoteStore;
+    use crate::http::keys::{SigningKey, TEST_KEYS};
+
+    dotenv().ok();
+    let auth = HttpAuthentication::with_fn(validator);
+    let jwt = JwtSettings::from_config(&load_config_from_env()).unwrap();
+
+    // Stub issuer publishing its discovery document and keys as files
+    let issuer = "https://sso.example.com/realms/playground";
+    let realm_key = SigningKey::from_pem("realm-rsa", &std::fs::read(format!("{}/rsa.pem", TEST_KEYS)).unwrap()).unwrap();
+    let directory = std::env::temp_dir().join(format!("oidc-{}", Uuid::new_v4()));
+    std::fs::create_dir(&directory).unwrap();
+    let jwks_path = directory.join("certs.json");
+    std::fs::write(&jwks_path, serde_json::to_vec(&JwkSet { keys: vec![realm_key.public_jwk().unwrap().clone()] }).unwrap()).unwrap();
+    let discovery_path = directory.join("openid-configuration.json");
+    std::fs::write(&discovery_path, serde_json::json!({"issuer": issuer, "jwks_uri": jwks_path}).to_string()).unwrap();
+
+    let oidc = OidcProvider::new(issuer, &discovery_path.to_string_lossy(), "playground", "realm_access.roles", jwt.verifications.clone());
+    oidc.refresh().unwrap();
+
+    let store: SharedQuoteStore = Arc::new(MemoryQuoteStore::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(store))
+            .app_data(web::Data::new(jwt.clone()))
+            .app_data(web::Data::new(oidc))
+            .app_data(web::Data::new(RevocationList::default()))
+            .wrap(auth)
+            .service(health_json)
+    ).await;
+    let claims = serde_json::json!({
+        "iss": issuer,
+        "aud": "playground",
+        "sub": "f3b6c1de",
+        "exp": Utc::now().timestamp() + 60,
+        "realm_access": {"roles": ["admin"]}
+    });
+    let call = |token: String| test::TestRequest::get().uri("/health")
+        .insert_header(ContentType::json())
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+
+    let resp = test::call_service(&app, call(realm_key.sign(&claims))).await;
+    assert!(resp.status().is_success());
+
+    // The issued tokens are still accepted
+    let resp = test::call_service(&app, call(jwt.sign("test", &["admin".to_string()], 60))).await;
+    assert!(resp.status().is_success());
+
+    // Tokens claiming the issuer are only verified with its keys
+    let resp = test::call_service(&app, call(jwt.keyring().signing_key.sign(&claims))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let challenge = resp.headers().get(header::WWW_AUTHENTICATE).unwrap().to_str().unwrap();
+    assert!(challenge.contains("No key verifies the token kid and alg"));
+
+    std::fs::remove_dir_all(directory).ok();
+}
+
+#[actix_web::test]
+async fn test_index_with_expired_jwt() {
+    use actix_web::test;
+    use actix_web::http::header;
+    use std::sync::Arc;
+    use crate::db::store::SharedQuoteStore;
+    use crate::db::store::memory::MemoryQuoteStore;
+
+    dotenv().ok();
+    let auth = HttpAuthentication::with_fn(validator);
+    let jwt = JwtSettings::from_config(&load_config_from_env()).unwrap();
+
+    let store: SharedQuoteStore = Arc::new(MemoryQuoteStore::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(store))
+            .app_data(web::Data::new(jwt.clone()))
+            .app_data(web::Data::new(RevocationList::default()))
+            .wrap(auth)
+            .service(health_json)
+    ).await;
+    let expired = jwt.sign("test", &["admin".to_string()], -(jwt.leeway_seconds + 60));
+    let req = test::TestRequest::get().uri("/health")
+        .insert_header(("Authorization", format!("Bearer {}", expired)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        resp.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+        r#"Bearer error="invalid_token", error_description="The token expired""#
+    );
+}
+
+#[actix_web::test]
+async fn test_index_with_revoked_jwt() {
+    use actix_web::test;
+    use actix_web::http::header;
+    use chrono::{DateTime, Utc};
+    use std::sync::Arc;
+    use crate::db::entities::revoked_token::RevokedToken;
+    use crate::db::store::SharedQuoteStore;
+    use crate::db::store::memory::MemoryQuoteStore;
+
+    dotenv().ok();
+    let auth = HttpAuthentication::with_fn(validator);
+    let jwt = JwtSettings::from_config(&load_config_from_env()).unwrap();
+    let revocations = web::Data::new(RevocationList::default());
+
+    let store: SharedQuoteStore = Arc::new(MemoryQuoteStore::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(store))
+            .app_data(web::Data::new(jwt.clone()))
+            .app_data(revocations.clone())
+            .wrap(auth)
+            .service(health_json)
+    ).await;
+    let token = jwt.sign("test", &["admin".to_string()], 60);
+    let claims = jwt.verify(&token).unwrap();
+    revocations.revoke(&[RevokedToken {
+        jti: claims.jti.unwrap(),
+        subject: claims.sub,
+        expires_at: DateTime::from_timestamp(claims.exp.unwrap(), 0).unwrap(),
+        revoked_at: Utc::now(),
+    }]);
+
+    let req = test::TestRequest::get().uri("/health")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        resp.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+        r#"Bearer error="invalid_token", error_description="The token was revoked""#
+    );
+}
+
+#[actix_web::test]
+async fn test_index_with_api_key() {
+    use actix_web::test;
+    use chrono::Utc;
+    use std::sync::Arc;
+    use uuid::Uuid;
+    use crate::db::entities::api_key::{generate_key, hash_key, ApiKey};
+    use crate::db::repositories::api_key::ApiKeyRepository;
+    use crate::db::store::SharedQuoteStore;
+    use crate::db::store::memory::MemoryQuoteStore;
+
+    dotenv().ok();
+    let auth = HttpAuthentication::with_fn(validator);
+    let pool = db::pool::build_db_pool(std::env::var("DATABASE_URL").expect("No DATABASE_URL configured"));
+    let key = generate_key();
+    ApiKeyRepository.insert(&ApiKey {
+        id: Uuid::new_v4().to_string(),
+        name: "nightly import".to_string(),
+        owner: "batch-import".to_string(),
+        key_hash: hash_key(&key),
+        scope: "quotes:read".to_string(),
+        created_at: Utc::now(),
+        expires_at: None,
+        last_used_at: None,
+        revoked_at: None,
+    }, &mut pool.get().unwrap()).unwrap();
+
+    let store: SharedQuoteStore = Arc::new(MemoryQuoteStore::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(store))
+            .app_data(web::Data::new(pool))
+            .app_data(web::Data::new(JwtSettings::from_config(&load_config_from_env()).unwrap()))
+            .app_data(web::Data::new(RevocationList::default()))
+            .wrap(auth)
+            .service(health_json)
+    ).await;
+
+    let req = test::TestRequest::get().uri("/health")
+        .insert_header((http::auth::API_KEY_HEADER, key))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::get().uri("/health")
+        .insert_header((http::auth::API_KEY_HEADER, generate_key()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_index_with_api_key_without_postgres() {
+    use actix_web::test;
+    use std::sync::Arc;
+    use crate::db::entities::api_key::generate_key;
+    use crate::db::store::SharedQuoteStore;
+    use crate::db::store::memory::MemoryQuoteStore;
+
+    dotenv().ok();
+    let auth = HttpAuthentication::with_fn(validator);
+    let store: SharedQuoteStore = Arc::new(MemoryQuoteStore::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(store))
+            .app_data(web::Data::new(JwtSettings::from_config(&load_config_from_env()).unwrap()))
+            .app_data(web::Data::new(RevocationList::default()))
+            .wrap(auth)
+            .service(health_json)
+    ).await;
+
+    let req = test::TestRequest::get().uri("/health")
+        .insert_header((http::auth::API_KEY_HEADER, generate_key()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_IMPLEMENTED);
+    let problem: ApiProblem = test::read_body_json(resp).await;
+    assert_eq!(problem.detail.as_deref(), Some("API keys need the postgres store, send a bearer token instead"));
+}
+
+#[cfg(feature = "sqlite")]
+#[actix_web::test]
+async fn test_health_with_pending_migrations() {
+    use actix_web::test;
+    use std::sync::Arc;
+    use crate::db::store::SharedQuoteStore;
+    use uuid::Uuid;
+    use crate::db::store::sqlite::SqliteQuoteStore;
+
+    let path = std::env::temp_dir().join(format!("quotes-{}.db", Uuid::new_v4()));
+    let store: SharedQuoteStore = Arc::new(SqliteQuoteStore::new(
+        db::pool::build_sqlite_pool(path.to_string_lossy().to_string())
+    ));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(store.clone()))
+            .service(health)
+    ).await;
+
+    let req = test::TestRequest::get().uri("/health").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(test::read_body(resp).await, "Pending migrations: 2026-10-18-165000_quotes, 2026-10-18-190000_quotes_created_by");
+
+    store.run_pending_migrations().unwrap();
+
+    let req = test::TestRequest::get().uri("/health").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    drop(app);
+    drop(store);
+    for suffix in ["", "-wal", "-shm"] {
+        std::fs::remove_file(format!("{}{}", path.to_string_lossy(), suffix)).ok();
+    }
+}